@@ -1,4 +1,4 @@
-use crate::errors::location::Location;
+use crate::errors::span::Span;
 
 #[macro_export]
 macro_rules! to_error {
@@ -30,52 +30,30 @@ macro_rules! to_suggestion {
 #[derive(Debug)]
 pub struct CompileError {
     err_lvl: ErrorLevel,
-    length: usize,
-    location: Location,
+    location: Span,
     message: String,
 }
 
 impl CompileError {
-    pub fn get(self) -> (Location, String, &'static str, usize) {
-        (
-            self.location,
-            self.message,
-            self.err_lvl.repr(),
-            self.length,
-        )
+    pub fn get(self) -> (Span, String, &'static str) {
+        (self.location, self.message, self.err_lvl.repr())
     }
 
     pub fn is_error(&self) -> bool {
         self.err_lvl == ErrorLevel::Error
     }
-
-    pub fn specify_length(&mut self, length: usize) {
-        self.length = length;
-    }
 }
 
-impl From<(Location, String, ErrorLevel, usize)> for CompileError {
-    fn from((location, message, err_lvl, length): (Location, String, ErrorLevel, usize)) -> Self {
+impl From<(Span, String, ErrorLevel)> for CompileError {
+    fn from((location, message, err_lvl): (Span, String, ErrorLevel)) -> Self {
         Self {
             err_lvl,
-            length,
             location,
             message,
         }
     }
 }
 
-impl From<(Location, String, ErrorLevel)> for CompileError {
-    fn from((location, message, err_lvl): (Location, String, ErrorLevel)) -> Self {
-        Self {
-            message,
-            length: 0,
-            location,
-            err_lvl,
-        }
-    }
-}
-
 #[derive(Debug, PartialEq, Eq)]
 pub enum ErrorLevel {
     Warning,
@@ -1,22 +1,23 @@
 use super::compile::CompileError;
-use std::collections::HashMap;
+use super::source_map::SourceMap;
 
-pub fn display_errors(errors: Vec<CompileError>, files: &[(String, &str)]) {
-    let mut files_status: HashMap<String, Vec<&str>> = HashMap::new();
-    for (filename, content) in files {
-        files_status.insert(filename.to_owned(), content.lines().collect());
-    }
+pub fn display_errors(errors: Vec<CompileError>, source_map: &SourceMap) {
     for error in errors {
-        let (location, message, err_lvl, length) = error.get();
-        let (filename, line_nb, column_nb) = location.get();
-        let code_lines = files_status
-            .get(&filename)
-            .expect("Never happens: File of error doesn't exist");
-        let code_line = code_lines
-            .get(line_nb - 1)
-            .expect("Never happens: given line of file that doesn't exist");
-        eprintln!("\n{filename}:{line_nb}:{column_nb}: {err_lvl}: {message}");
+        let (location, message, err_lvl) = error.get();
+        let (line_nb, column_nb) = source_map.line_col(location);
+        let code_line = source_map.line(location.file_id(), line_nb);
+        // the underline stops at the end of the line for multi-line spans
+        let length = location
+            .text(source_map.content(location.file_id()))
+            .lines()
+            .next()
+            .map_or(0, |text| text.chars().count());
+        eprintln!("\n{}: {err_lvl}: {message}", source_map.display(location));
         eprintln!("{line_nb:5} | {code_line}");
-        eprintln!("{}^{}", " ".repeat(8 + column_nb - 1), "~".repeat(length));
+        eprintln!(
+            "{}^{}",
+            " ".repeat(8 + column_nb - 1),
+            "~".repeat(length.saturating_sub(1))
+        );
     }
 }
@@ -1,3 +1,4 @@
 pub mod compile;
 pub mod display;
-pub mod location;
+pub mod source_map;
+pub mod span;
@@ -0,0 +1,112 @@
+use super::span::{FileId, LineIndex, Span};
+extern crate alloc;
+use alloc::rc::Rc;
+
+#[derive(Debug)]
+struct SourceFile {
+    content: Rc<str>,
+    lines: LineIndex,
+    name: String,
+}
+
+/// Line set by a `#line` directive, from the line following the directive.
+#[derive(Debug)]
+struct LineMarker {
+    file_id: FileId,
+    from_line: usize,
+    presumed_file: String,
+    presumed_line: usize,
+}
+
+/// Owner of the compiled files, that resolves spans into file names, lines
+/// and columns.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+    markers: Vec<LineMarker>,
+}
+
+impl SourceMap {
+    pub fn add_file(&mut self, name: String, content: String) -> FileId {
+        let file_id = FileId::new(self.files.len());
+        self.files.push(SourceFile {
+            lines: LineIndex::new(&content),
+            content: content.into(),
+            name,
+        });
+        file_id
+    }
+
+    /// Registers a `#line` directive: the line following `directive` becomes
+    /// line `presumed_line` of `file`, or of the current file.
+    pub fn add_line_marker(&mut self, directive: Span, presumed_line: usize, file: Option<String>) {
+        let presumed_file = file.unwrap_or_else(|| self.presumed(directive).0.to_owned());
+        let end = Span::point(directive.file_id(), directive.end());
+        self.markers.push(LineMarker {
+            file_id: directive.file_id(),
+            from_line: self.line_col(end).0 + 1,
+            presumed_file,
+            presumed_line,
+        });
+    }
+
+    pub fn content(&self, file_id: FileId) -> &str {
+        self.file(file_id).map_or("", |file| &file.content)
+    }
+
+    /// Returns the location of `span` as `file:line:column`, following `#line`.
+    pub fn display(&self, span: Span) -> String {
+        let (file, line) = self.presumed(span);
+        format!("{file}:{line}:{}", self.line_col(span).1)
+    }
+
+    /// Returns the content of a file, for the tokens to borrow from while the map
+    /// is being updated.
+    pub fn source(&self, file_id: FileId) -> Rc<str> {
+        self.file(file_id)
+            .map_or_else(|| Rc::from(""), |file| Rc::clone(&file.content))
+    }
+
+    fn file(&self, file_id: FileId) -> Option<&SourceFile> {
+        self.files.get(file_id.index())
+    }
+
+    /// Returns the text of `line` (from 1), without its line terminator.
+    pub fn line(&self, file_id: FileId, line: usize) -> &str {
+        self.file(file_id).map_or("", |file| {
+            file.content
+                .get(file.lines.line_start(line)..)
+                .and_then(|rest| rest.lines().next())
+                .unwrap_or_default()
+        })
+    }
+
+    /// Returns the line and column (both from 1) of the start of `span`.
+    pub fn line_col(&self, span: Span) -> (usize, usize) {
+        self.file(span.file_id()).map_or((1, 1), |file| {
+            file.lines.line_col(&file.content, span.start())
+        })
+    }
+
+    pub fn name(&self, file_id: FileId) -> &str {
+        self.file(file_id).map_or("<unknown>", |file| &file.name)
+    }
+
+    /// Returns the file and line of the start of `span`, following `#line`.
+    pub fn presumed(&self, span: Span) -> (&str, usize) {
+        let line = self.line_col(span).0;
+        self.markers
+            .iter()
+            .rev()
+            .find(|marker| marker.file_id == span.file_id() && marker.from_line <= line)
+            .map_or_else(
+                || (self.name(span.file_id()), line),
+                |marker| {
+                    (
+                        marker.presumed_file.as_str(),
+                        marker.presumed_line + line - marker.from_line,
+                    )
+                },
+            )
+    }
+}
@@ -0,0 +1,89 @@
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId(usize);
+
+impl FileId {
+    pub const fn new(index: usize) -> Self {
+        Self(index)
+    }
+
+    pub const fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Byte offsets of the start and of the end (excluded) of a part of a file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    file_id: FileId,
+    start: usize,
+    end: usize,
+}
+
+impl Span {
+    pub const fn new(file_id: FileId, start: usize, end: usize) -> Self {
+        Self {
+            file_id,
+            start,
+            end,
+        }
+    }
+
+    pub const fn point(file_id: FileId, offset: usize) -> Self {
+        Self::new(file_id, offset, offset)
+    }
+
+    pub const fn file_id(&self) -> FileId {
+        self.file_id
+    }
+
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    pub const fn end(&self) -> usize {
+        self.end
+    }
+
+    pub const fn to(self, other: Self) -> Self {
+        Self::new(self.file_id, self.start, other.end)
+    }
+
+    pub fn text(self, content: &str) -> &str {
+        content.get(self.start..self.end).unwrap_or_default()
+    }
+}
+
+/// Offsets of the starts of the lines of a file.
+#[derive(Debug, Default)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(content: &str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(content.match_indices('\n').map(|(idx, _)| idx + 1));
+        Self { line_starts }
+    }
+
+    /// Returns the line (from 1) containing `offset`.
+    pub fn line(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|start| *start <= offset)
+    }
+
+    pub fn line_start(&self, line: usize) -> usize {
+        self.line_starts
+            .get(line.saturating_sub(1))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the line and column (from 1) of `offset`, counting characters.
+    pub fn line_col(&self, content: &str, offset: usize) -> (usize, usize) {
+        let line = self.line(offset);
+        let column = content
+            .get(self.line_start(line)..offset)
+            .map_or(0, |before| before.chars().count());
+        (line, column + 1)
+    }
+}
@@ -1,4 +1,8 @@
 #![allow(clippy::pub_use)]
 
 pub use super::numbers::types;
+pub use super::types::keywords;
+pub use super::types::lexing_options;
+pub use super::types::source_encoding;
+pub use super::types::standard;
 pub use super::types::tokens_types;
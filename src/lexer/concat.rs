@@ -0,0 +1,82 @@
+use super::types::tokens_types::{Encoding, StrValue, Token, TokenValue};
+use crate::errors::compile::{CompileError, Res};
+use crate::errors::source_map::SourceMap;
+use crate::to_error;
+use core::mem;
+extern crate alloc;
+use alloc::borrow::Cow;
+
+fn concat_encoding(first: Encoding, second: Encoding) -> Option<Encoding> {
+    match (first, second) {
+        (Encoding::Ordinary, other) | (other, Encoding::Ordinary) => Some(other),
+        _ if first == second => Some(first),
+        _ => None,
+    }
+}
+
+fn append<'src>(
+    literal: &mut Token<'src>,
+    mut piece: Token<'src>,
+    source_map: &SourceMap,
+    errors: &mut Vec<CompileError>,
+) {
+    let location = *piece.get_location();
+    // pieces from different files have no source text in common
+    if location.file_id() == literal.get_location().file_id() {
+        let span = literal.get_location().to(location);
+        *literal.location_mut() = span;
+        *literal.lexeme_mut() =
+            Cow::Owned(span.text(source_map.content(span.file_id())).to_owned());
+        *literal.trailing_trivia_mut() = mem::take(piece.trailing_trivia_mut());
+    }
+    if let (TokenValue::Str(encoding, value), TokenValue::Str(piece_encoding, piece_str)) =
+        (literal.value_mut(), piece.into_value())
+    {
+        if let Some(concatenated) = concat_encoding(*encoding, piece_encoding) {
+            *encoding = concatenated;
+        } else {
+            errors.push(to_error!(
+                location,
+                "Cannot concatenate string literals with incompatible encoding prefixes '{}' and '{}'.",
+                encoding.prefix(),
+                piece_encoding.prefix()
+            ));
+        }
+        *value = match (mem::take(value), piece_str) {
+            (StrValue::Text(mut text), StrValue::Text(piece_text)) => {
+                text.to_mut().push_str(&piece_text);
+                StrValue::Text(text)
+            }
+            (first, second) => {
+                let mut units = first.into_units(*encoding);
+                units.extend(second.into_units(*encoding));
+                StrValue::from_units(*encoding, units)
+            }
+        };
+    }
+}
+
+/// Concatenates adjacent string literals (translation phase 6), the lexeme
+/// being the source text from the first piece to the last.
+pub fn concat_strings<'src>(
+    tokens: Vec<Token<'src>>,
+    source_map: &SourceMap,
+) -> Res<Vec<Token<'src>>> {
+    let mut errors = vec![];
+    let mut result: Vec<Token> = Vec::with_capacity(tokens.len());
+    // index in `result` of the literal being concatenated
+    let mut literal = None;
+    for token in tokens {
+        let is_str = matches!(token.get_value(), TokenValue::Str(..));
+        match literal.and_then(|idx| result.get_mut(idx)) {
+            Some(previous) if is_str => append(previous, token, source_map, &mut errors),
+            Some(_) | None => {
+                if !matches!(token.get_value(), TokenValue::Comment(_)) {
+                    literal = is_str.then_some(result.len());
+                }
+                result.push(token);
+            }
+        }
+    }
+    Res::from((result, errors))
+}
@@ -0,0 +1,166 @@
+use super::types::lexing_options::LexingOptions;
+use super::types::source_encoding::SourceEncoding;
+use crate::errors::compile::{CompileError, Res};
+use crate::errors::source_map::SourceMap;
+use crate::errors::span::{FileId, Span};
+use crate::to_error;
+extern crate alloc;
+use alloc::borrow::Cow;
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Characters of the bytes `0x80` to `0x9F` in Windows-1252.
+const WINDOWS_1252: [Option<char>; 32] = [
+    Some('\u{20ac}'),
+    None,
+    Some('\u{201a}'),
+    Some('\u{192}'),
+    Some('\u{201e}'),
+    Some('\u{2026}'),
+    Some('\u{2020}'),
+    Some('\u{2021}'),
+    Some('\u{2c6}'),
+    Some('\u{2030}'),
+    Some('\u{160}'),
+    Some('\u{2039}'),
+    Some('\u{152}'),
+    None,
+    Some('\u{17d}'),
+    None,
+    None,
+    Some('\u{2018}'),
+    Some('\u{2019}'),
+    Some('\u{201c}'),
+    Some('\u{201d}'),
+    Some('\u{2022}'),
+    Some('\u{2013}'),
+    Some('\u{2014}'),
+    Some('\u{2dc}'),
+    Some('\u{2122}'),
+    Some('\u{161}'),
+    Some('\u{203a}'),
+    Some('\u{153}'),
+    None,
+    Some('\u{17e}'),
+    Some('\u{178}'),
+];
+
+#[derive(Default)]
+struct Decoded {
+    invalid: Vec<(usize, Vec<u8>)>,
+    text: String,
+}
+
+impl Decoded {
+    fn push_invalid(&mut self, bytes: &[u8]) {
+        self.invalid.push((self.text.len(), bytes.to_vec()));
+        self.text.push(char::REPLACEMENT_CHARACTER);
+    }
+}
+
+/// Replaces the `\r
+/// ` and lone `\r` line terminators with `
+/// `, before
+/// decoding as the 3 encodings spell them the same way.
+fn normalise_newlines(bytes: &[u8]) -> Cow<'_, [u8]> {
+    if !bytes.contains(&b'\r') {
+        return Cow::Borrowed(bytes);
+    }
+    let mut normalised = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter().peekable();
+    while let Some(byte) = iter.next() {
+        if *byte == b'\r' {
+            iter.next_if_eq(&&b'\n');
+            normalised.push(b'\n');
+        } else {
+            normalised.push(*byte);
+        }
+    }
+    Cow::Owned(normalised)
+}
+
+fn decode_utf8(bytes: &[u8]) -> Decoded {
+    let mut decoded = Decoded::default();
+    for chunk in bytes.utf8_chunks() {
+        decoded.text.push_str(chunk.valid());
+        if !chunk.invalid().is_empty() {
+            decoded.push_invalid(chunk.invalid());
+        }
+    }
+    decoded
+}
+
+fn windows_1252(byte: u8) -> Option<char> {
+    match byte {
+        0x80..=0x9F => WINDOWS_1252
+            .get(usize::from(byte - 0x80))
+            .copied()
+            .flatten(),
+        _ => Some(char::from(byte)),
+    }
+}
+
+fn decode_single_byte(bytes: &[u8], decode_byte: fn(u8) -> Option<char>) -> Decoded {
+    let mut decoded = Decoded::default();
+    decoded.text.reserve(bytes.len());
+    for byte in bytes {
+        match decode_byte(*byte) {
+            Some(valid) => decoded.text.push(valid),
+            None => decoded.push_invalid(&[*byte]),
+        }
+    }
+    decoded
+}
+
+fn display_bytes(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len() * 4);
+    for byte in bytes {
+        text.push_str("\\x");
+        for digit in [byte >> 4u8, byte & 0xF] {
+            let hex = char::from_digit(u32::from(digit), 16).unwrap_or_default();
+            text.push(hex.to_ascii_uppercase());
+        }
+    }
+    text
+}
+
+/// Decodes the bytes of a file, and registers it in `source_map`.
+///
+/// A UTF-8 byte order mark overrides [`LexingOptions::encoding`]. The invalid
+/// byte sequences are replaced with U+FFFD, with an error for each of them.
+pub fn decode_file(
+    source_map: &mut SourceMap,
+    name: String,
+    bytes: &[u8],
+    options: &LexingOptions,
+) -> Res<FileId> {
+    let (encoding, content) = bytes
+        .strip_prefix(BOM)
+        .map_or((options.encoding, bytes), |rest| {
+            (SourceEncoding::Utf8, rest)
+        });
+    let normalised = normalise_newlines(content);
+    let Decoded { invalid, text } = match encoding {
+        SourceEncoding::Utf8 => decode_utf8(&normalised),
+        SourceEncoding::Latin1 => decode_single_byte(&normalised, |byte| Some(char::from(byte))),
+        SourceEncoding::Windows1252 => decode_single_byte(&normalised, windows_1252),
+    };
+    let file_id = source_map.add_file(name, text);
+    let errors: Vec<CompileError> = invalid
+        .into_iter()
+        .map(|(offset, sequence)| {
+            let location = Span::new(
+                file_id,
+                offset,
+                offset + char::REPLACEMENT_CHARACTER.len_utf8(),
+            );
+            to_error!(
+                location,
+                "Invalid {} byte sequence '{}': replaced by U+FFFD.",
+                encoding.repr(),
+                display_bytes(&sequence)
+            )
+        })
+        .collect();
+    Res::from((file_id, errors))
+}
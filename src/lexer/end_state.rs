@@ -1,17 +1,26 @@
+use super::identifiers::identifier_name;
 use super::numbers::literal_to_number;
 use super::types::lexing_data::LexingData;
 use super::types::lexing_state::{Ident, LexingStatus, SymbolStatus};
-use super::types::tokens_types::Token;
-use crate::errors::location::Location;
-use crate::to_error;
+use super::types::standard::Standard;
+use super::types::tokens_types::{CharConstant, Encoding, StrValue, Token, TokenValue};
+use crate::errors::span::Span;
+use crate::{to_error, to_warning};
 use core::mem;
 
-pub fn end_symbols(symbols: &mut SymbolStatus, lex_data: &mut LexingData, location: &Location) {
+pub fn end_symbols(symbols: &mut SymbolStatus, lex_data: &mut LexingData, location: &Span) {
     let mut idx: usize = 0;
     while !symbols.is_empty() && idx <= 2 {
         idx += 1;
-        if let Some((size, symbol)) = symbols.try_to_operator() {
-            let token = Token::from_symbol(symbol, size, location);
+        let start = lex_data.source_start(location.start(), symbols.len());
+        if let Some((lexeme, symbol)) = symbols.try_to_operator(lex_data.standard()) {
+            let end = lex_data.source_end(start, lexeme.len());
+            let span = Span::new(location.file_id(), start, end);
+            let token = Token::from_symbol(
+                symbol,
+                lex_data.borrow_source(&span, || lexeme.to_owned()),
+                span,
+            );
             lex_data.push_token(token);
         } else {
             panic!(
@@ -22,36 +31,115 @@ pub fn end_symbols(symbols: &mut SymbolStatus, lex_data: &mut LexingData, locati
     }
 }
 
-fn end_ident(literal: &mut Ident, lex_data: &mut LexingData, location: &Location) {
+fn end_ident(literal: &mut Ident, lex_data: &mut LexingData, location: &Span) {
     if !literal.is_empty() {
-        let possible_number = literal_to_number(lex_data, literal, location);
+        let span = lex_data.span_before(location, literal.len());
+        let possible_number = literal_to_number(lex_data, literal, &span);
         match possible_number {
+            None if literal.is_number() => lex_data.push_error_token(span),
             None => {
-                let token = Token::from_identifier(literal, location);
+                let lexeme = lex_data.borrow_source(&span, || literal.value().to_owned());
+                let name = identifier_name(lexeme.clone());
+                let token = Token::from_identifier(name, lexeme, span, lex_data);
+                if let TokenValue::Keyword(keyword) = token.get_value() {
+                    if let Some(spelling) = keyword.c23_spelling() {
+                        if lex_data.standard() >= Standard::C23 {
+                            lex_data.push_err(to_warning!(
+                                span,
+                                "'{}' is deprecated in C23: use '{spelling}' instead.",
+                                keyword.repr()
+                            ));
+                        }
+                    }
+                }
                 lex_data.push_token(token);
             }
             Some(nb) => {
-                let token = Token::from_number(nb, location);
+                let lexeme = lex_data.borrow_source(&span, || literal.value().to_owned());
+                let token = Token::from_number(nb, lexeme, span);
                 lex_data.push_token(token);
             }
         }
+        lex_data.recycle_ident_buffer(literal.take_value());
+    }
+}
+
+fn end_char(
+    encoding: Encoding,
+    units: Vec<u32>,
+    invalid: bool,
+    lex_data: &mut LexingData,
+    location: &Span,
+) {
+    let (lexeme, span) = lex_data.take_lexeme(location);
+    if invalid {
+        // the invalid escape sequence was already reported
+        lex_data.push_error_token(span);
+        return;
+    }
+    match (encoding, units.len()) {
+        (_, 0) => {
+            lex_data.push_err(to_error!(
+                span,
+                "Found an empty char, but chars must contain one character. Did you mean '\\''?"
+            ));
+            lex_data.push_error_token(span);
+            return;
+        }
+        (_, 1) => (),
+        (Encoding::Ordinary, 2..=4) => lex_data.push_err(to_warning!(
+            span,
+            "Multi-character character constant: its value is implementation-defined."
+        )),
+        (Encoding::Ordinary, _) => lex_data.push_err(to_warning!(
+            span,
+            "Character constant too long for its type: only the last 4 characters are used."
+        )),
+        (Encoding::Wide, _) => lex_data.push_err(to_warning!(
+            span,
+            "Multi-character wide character constant: only the last character is used."
+        )),
+        (Encoding::Utf8 | Encoding::Utf16 | Encoding::Utf32, _) => {
+            lex_data.push_err(to_error!(
+                span,
+                "A '{}' character constant must contain a single code unit.",
+                encoding.char_type()
+            ));
+            lex_data.push_error_token(span);
+            return;
+        }
     }
+    lex_data.push_token(Token::from_char(
+        CharConstant::new(encoding, units),
+        lexeme,
+        span,
+    ));
 }
 
-pub fn end_current(status: &mut LexingStatus, lex_data: &mut LexingData, location: &Location) {
+pub fn end_current(status: &mut LexingStatus, lex_data: &mut LexingData, location: &Span) {
     match status {
         LexingStatus::Comment(_) | LexingStatus::Unset | LexingStatus::StartOfLine => return,
         LexingStatus::Symbols(symbol_status) => end_symbols(symbol_status, lex_data, location),
         LexingStatus::Identifier(ident) => end_ident(ident, lex_data, location),
-        LexingStatus::Char(None) => {
-            lex_data.push_err(to_error!(
-                location,
-                "Found an empty char, but chars must contain one character. Did you mean '\\''?"
-            ));
+        LexingStatus::Char(encoding, units, invalid) => {
+            end_char(*encoding, mem::take(units), *invalid, lex_data, location);
         }
-        LexingStatus::Char(Some(ch)) => lex_data.push_token(Token::from_char(*ch, location)),
-        LexingStatus::Str(val) => {
-            lex_data.push_token(Token::from_str(mem::take(val), location));
+        LexingStatus::Str(encoding, val) => {
+            let (lexeme, span) = lex_data.take_lexeme(location);
+            // between the prefix and the quotes
+            let content = Span::new(
+                span.file_id(),
+                lex_data.source_end(span.start(), encoding.prefix().len() + 1),
+                span.end().saturating_sub(1),
+            );
+            let value = match val.take() {
+                None => StrValue::Text(
+                    lex_data.borrow_source(&content, || lex_data.rewritten(&content)),
+                ),
+                Some(StrValue::Units(units)) => StrValue::from_units(*encoding, units),
+                Some(text @ StrValue::Text(_)) => text,
+            };
+            lex_data.push_token(Token::from_str(*encoding, value, lexeme, span));
         }
     };
     *status = LexingStatus::Unset;
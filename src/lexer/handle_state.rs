@@ -1,13 +1,15 @@
 use super::types::{
     escape_state::{EscapeSequence, EscapeStatus},
     lexing_data::LexingData,
+    lexing_state::LexingStatus,
+    tokens_types::Encoding,
 };
-use crate::{errors::location::Location, safe_parse_int, to_error};
+use crate::{errors::span::Span, to_error, to_warning};
 
 fn end_unicode_sequence(
     lex_data: &mut LexingData,
     value: &str,
-    location: &Location,
+    location: &Span,
 ) -> Result<char, ()> {
     match crate::safe_parse_int!(
         "Invalid escaped unicode number: ",
@@ -32,14 +34,14 @@ fn expect_min_length(
     lex_data: &mut LexingData,
     size: usize,
     value: &str,
-    location: &Location,
+    location: &Span,
     sequence: &EscapeSequence,
 ) -> Result<(), ()> {
     let len = value.len();
     if len < size {
         lex_data.push_err(to_error!(
             location,
-            "Invalid escaped {} number: must contain 4 digits, but found only {}",
+            "Invalid escaped {} number: must contain {size} digits, but found only {}",
             sequence.repr(),
             len
         ));
@@ -52,16 +54,38 @@ fn expect_max_length(size: usize, value: &str) {
     assert!(value.len() <= size, "Never should have pushed here");
 }
 
+fn end_numeric_sequence(
+    lex_data: &mut LexingData,
+    location: &Span,
+    sequence: &EscapeSequence,
+    radix: u32,
+    encoding: Encoding,
+) -> Result<u32, ()> {
+    let value = sequence.value();
+    u32::from_str_radix(value, radix)
+        .ok()
+        .filter(|int| *int <= encoding.max_escape_value())
+        .ok_or_else(|| {
+            lex_data.push_err(to_error!(
+                location,
+                "Escape sequence '{}{value}' is out of range for type '{}'.",
+                sequence.prefix(),
+                encoding.element_type()
+            ));
+        })
+}
+
 pub fn end_escape_sequence(
     lex_data: &mut LexingData,
-    location: &Location,
+    location: &Span,
     sequence: &EscapeSequence,
-) -> Result<char, ()> {
+    encoding: Encoding,
+) -> Result<u32, ()> {
     match &sequence {
         EscapeSequence::ShortUnicode(ref value) => {
             expect_max_length(4, value);
             expect_min_length(lex_data, 4, value, location, sequence)?;
-            end_unicode_sequence(lex_data, value, location)
+            end_unicode_sequence(lex_data, value, location).map(u32::from)
         }
         EscapeSequence::Unicode(ref value) => {
             if value.len() <= 4 {
@@ -74,134 +98,158 @@ pub fn end_escape_sequence(
             }
             expect_max_length(8, value);
             expect_min_length(lex_data, 8, value, location, sequence)?;
-            end_unicode_sequence(lex_data, value, location)
+            end_unicode_sequence(lex_data, value, location).map(u32::from)
         }
         EscapeSequence::Hexadecimal(ref value) => {
-            expect_max_length(3, value);
-            expect_min_length(lex_data, 2, value, location, sequence)?;
-            let int =
-                u8::from_str_radix(value, 16).expect("We push only numeric so this doesn't happen");
-            Ok(int.into())
+            expect_min_length(lex_data, 1, value, location, sequence)?;
+            end_numeric_sequence(lex_data, location, sequence, 16, encoding)
         }
         EscapeSequence::Octal(ref value) => {
             expect_max_length(3, value);
             expect_min_length(lex_data, 1, value, location, sequence)?;
-            match safe_parse_int!(
-                "Invalid octal escape sequence :",
-                u32,
-                location,
-                u32::from_str_radix(value, 8)
-            ) {
-                Ok(int) if value.len() < 3 || int <= 0o377 =>
-                {
-                    #[allow(
-                        clippy::as_conversions,
-                        clippy::cast_possible_truncation,
-                        reason = "int <= 255"
-                    )]
-                    Ok(char::from(int as u8))
-                }
-                Ok(_) => {
-                    #[allow(clippy::string_slice, reason = "len = 3")]
-                    match safe_parse_int!(
-                        "Invalid octal escape sequence: ",
-                        u8,
-                        location,
-                        u8::from_str_radix(&value[0..2], 8)
-                    ) {
-                        Ok(octal_int) => Ok(char::from(octal_int)),
-                        Err(err) => {
-                            lex_data.push_err(err);
-                            Err(())
-                        }
-                    }
-                }
-                Err(err) => {
-                    lex_data.push_err(err);
-                    Err(())
-                }
-            }
+            end_numeric_sequence(lex_data, location, sequence, 8, encoding)
         }
     }
 }
 
+/// Pushes `ch` to the sequence, and returns its value once it is over.
 fn handle_escaped_sequence(
     ch: char,
     escape_sequence: &mut EscapeSequence,
     lex_data: &mut LexingData,
-    location: &Location,
-) -> Option<char> {
-    if !ch.is_ascii_hexdigit() || (escape_sequence.is_octal() && !ch.is_ascii_octdigit()) {
-        end_escape_sequence(lex_data, location, escape_sequence).ok()
-    } else {
-        let value = escape_sequence.value_mut();
-        value.push(ch);
-        if value.len() == escape_sequence.max_len() {
-            end_escape_sequence(lex_data, location, escape_sequence).ok()
-        } else {
-            None
-        }
+    location: &Span,
+    encoding: Encoding,
+) -> Option<Result<u32, ()>> {
+    if !escape_sequence.accepts(ch) {
+        return Some(end_escape_sequence(
+            lex_data,
+            location,
+            escape_sequence,
+            encoding,
+        ));
     }
+    escape_sequence.value_mut().push(ch);
+    (escape_sequence.value().len() == escape_sequence.max_len())
+        .then(|| end_escape_sequence(lex_data, location, escape_sequence, encoding))
 }
 
+/// Handles the character after a backslash. An unknown escape sequence is the
+/// character itself in a string, as for GCC, but an error in a char.
 fn handle_escape_one_char(
     ch: char,
     lex_data: &mut LexingData,
     escape_status: &mut EscapeStatus,
-    location: &Location,
-) -> Option<char> {
+    location: &Span,
+    string: bool,
+) -> Result<Option<char>, ()> {
     *escape_status = EscapeStatus::False;
     match ch {
-        '\0' => Some('\0'),
-        'a' => Some('\u{0007}'),  // alert (bepp, bell)
-        'b' => Some('\u{0008}'),  // backspace
-        't' => Some('\u{0009}'),  // horizontal tab
-        'n' => Some('\u{000A}'),  // newline (line feed)
-        'v' => Some('\u{000B}'),  // vertical tab
-        'f' => Some('\u{000C}'),  // formfeed page break
-        'r' => Some('\u{000D}'),  // carriage return
-        'e' => Some('\u{001B}'),  // escape character
-        '"' => Some('\u{0022}'),  // double quotation mark
-        '\'' => Some('\u{0027}'), // apostrophe or single quotiation mark
-        '?' => Some('\u{003F}'),  // question mark (used to avoid tigraphs)
-        '\\' => Some('\u{005C}'), // backslash
+        '\0' => Ok(Some('\0')),
+        'a' => Ok(Some('\u{0007}')),  // alert (bepp, bell)
+        'b' => Ok(Some('\u{0008}')),  // backspace
+        't' => Ok(Some('\u{0009}')),  // horizontal tab
+        'n' => Ok(Some('\u{000A}')),  // newline (line feed)
+        'v' => Ok(Some('\u{000B}')),  // vertical tab
+        'f' => Ok(Some('\u{000C}')),  // formfeed page break
+        'r' => Ok(Some('\u{000D}')),  // carriage return
+        'e' => Ok(Some('\u{001B}')),  // escape character
+        '"' => Ok(Some('\u{0022}')),  // double quotation mark
+        '\'' => Ok(Some('\u{0027}')), // apostrophe or single quotiation mark
+        '?' => Ok(Some('\u{003F}')),  // question mark (used to avoid tigraphs)
+        '\\' => Ok(Some('\u{005C}')), // backslash
         'u' => {
             *escape_status = EscapeStatus::Sequence(EscapeSequence::ShortUnicode(String::new()));
-            None
+            Ok(None)
         }
         'U' => {
             *escape_status = EscapeStatus::Sequence(EscapeSequence::Unicode(String::new()));
-            None
+            Ok(None)
         }
         'x' => {
             *escape_status = EscapeStatus::Sequence(EscapeSequence::Hexadecimal(String::new()));
-            None
+            Ok(None)
         }
-        _ if ch.is_numeric() => {
+        _ if ch.is_ascii_octdigit() => {
             *escape_status = EscapeStatus::Sequence(EscapeSequence::Octal(ch.to_string()));
-            None
+            Ok(None)
+        }
+        _ if string => {
+            lex_data.push_err(to_warning!(
+                location,
+                "Unknown escape sequence '\\{ch}': '{ch}' is used instead."
+            ));
+            Ok(Some(ch))
         }
         _ => {
             lex_data.push_err(to_error!(
                 location,
                 "Character '{ch}' can not be escaped, even inside a string or a char."
             ));
-            None
+            Err(())
         }
     }
 }
 
+/// Returns the value of the escape sequence once it is over, or `Err` if it
+/// was invalid.
 pub fn handle_escape(
     ch: char,
     lex_data: &mut LexingData,
     escape_status: &mut EscapeStatus,
-    location: &Location,
-) -> Option<char> {
+    location: &Span,
+    encoding: Encoding,
+    string: bool,
+) -> Result<Option<u32>, ()> {
     match escape_status {
         EscapeStatus::Sequence(escape_sequence) => {
-            handle_escaped_sequence(ch, escape_sequence, lex_data, location)
+            let Some(escaped) =
+                handle_escaped_sequence(ch, escape_sequence, lex_data, location, encoding)
+            else {
+                return Ok(None);
+            };
+            *escape_status = EscapeStatus::False;
+            escaped.map(Some)
+        }
+        EscapeStatus::Single => {
+            handle_escape_one_char(ch, lex_data, escape_status, location, string)
+                .map(|escaped| escaped.map(u32::from))
         }
-        EscapeStatus::Single => handle_escape_one_char(ch, lex_data, escape_status, location),
         EscapeStatus::False => panic!("never called"),
     }
 }
+
+/// Pushes the value of an escape sequence, that is a code unit for octal and
+/// hexadecimal ones.
+pub fn push_escaped_value(
+    value: u32,
+    numeric: bool,
+    status: &mut LexingStatus,
+    lex_data: &mut LexingData,
+    location: &Span,
+) {
+    match (status, char::from_u32(value)) {
+        (LexingStatus::Char(_, units, _), _) if numeric => units.push(value),
+        (LexingStatus::Char(encoding, units, _), Some(ch)) => {
+            units.extend(encoding.code_units(ch));
+        }
+        (LexingStatus::Str(encoding, Some(val)), _) if numeric => val.push_unit(value, *encoding),
+        (LexingStatus::Str(encoding, Some(val)), Some(ch)) => {
+            val.push_str(ch.encode_utf8(&mut [0; 4]), *encoding);
+        }
+        (LexingStatus::Str(_, None), _) => {
+            panic!("the value of a string is built when its first escape sequence starts")
+        }
+        (LexingStatus::Char(..) | LexingStatus::Str(..), None) => lex_data.push_err(to_error!(
+            location,
+            "Escaped value {value:#x} is not a unicode scalar value, which is not supported in string literals."
+        )),
+        (
+            LexingStatus::StartOfLine
+            | LexingStatus::Unset
+            | LexingStatus::Symbols(_)
+            | LexingStatus::Identifier(_)
+            | LexingStatus::Comment(_),
+            _,
+        ) => panic!("escape sequences are only lexed in strings and chars"),
+    }
+}
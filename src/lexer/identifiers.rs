@@ -0,0 +1,84 @@
+use unicode_ident::{is_xid_continue, is_xid_start};
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization as _};
+extern crate alloc;
+use alloc::borrow::Cow;
+
+pub fn is_identifier_start(ch: char) -> bool {
+    ch == '_' || is_xid_start(ch)
+}
+
+pub fn is_identifier_continue(ch: char) -> bool {
+    is_xid_continue(ch)
+}
+
+/// Parses the `\uXXXX` or `\UXXXXXXXX` at the start of `rest`, and returns its
+/// length with its code point.
+pub fn universal_character_name(rest: &str) -> Option<(usize, u32)> {
+    let mut chars = rest.chars();
+    if chars.next() != Some('\\') {
+        return None;
+    }
+    let digits = match chars.next()? {
+        'u' => 4,
+        'U' => 8,
+        _ => return None,
+    };
+    let hex = rest.get(2..2 + digits)?;
+    if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some((2 + digits, value))
+}
+
+/// Checks if `rest` starts with `\u` or `\U` followed by too few hexadecimal
+/// digits, and returns its length with the number of digits it needs.
+pub fn incomplete_ucn(rest: &str) -> Option<(usize, usize)> {
+    let digits = match rest.get(..2)? {
+        "\\u" => 4,
+        "\\U" => 8,
+        _ => return None,
+    };
+    let found = rest
+        .bytes()
+        .skip(2)
+        .take(digits)
+        .take_while(u8::is_ascii_hexdigit)
+        .count();
+    (found < digits).then_some((2 + found, digits))
+}
+
+/// Checks if a universal character name can be part of an identifier, and
+/// returns why it can't otherwise.
+pub fn check_identifier_ucn(value: u32, start: bool) -> Result<char, &'static str> {
+    let ch = char::from_u32(value).ok_or("it isn't a valid unicode character")?;
+    if value < 0xA0 && !matches!(ch, '$' | '@' | '`') {
+        Err("it designates a character of the basic character set")
+    } else if start && !is_identifier_start(ch) {
+        Err("it can't start an identifier")
+    } else if !is_identifier_continue(ch) {
+        Err("it can't be part of an identifier")
+    } else {
+        Ok(ch)
+    }
+}
+
+/// Returns the name of an identifier, with its universal character names
+/// decoded, normalised to NFC.
+pub fn identifier_name(spelling: Cow<'_, str>) -> Cow<'_, str> {
+    if !spelling.contains('\\')
+        && (spelling.is_ascii() || is_nfc_quick(spelling.chars()) == IsNormalized::Yes)
+    {
+        return spelling;
+    }
+    let mut decoded = String::with_capacity(spelling.len());
+    let mut rest = spelling.as_ref();
+    while let Some(ch) = rest.chars().next() {
+        let (len, decoded_ch) = universal_character_name(rest)
+            .and_then(|(len, value)| Some((len, char::from_u32(value)?)))
+            .unwrap_or_else(|| (ch.len_utf8(), ch));
+        decoded.push(decoded_ch);
+        rest = rest.get(len..).unwrap_or_default();
+    }
+    Cow::Owned(decoded.nfc().collect())
+}
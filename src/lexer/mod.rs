@@ -1,17 +1,36 @@
 pub mod api;
+pub mod concat;
+pub mod decode;
 mod end_state;
 mod handle_state;
+mod identifiers;
 mod numbers;
+mod trigraphs;
+mod trivia;
 mod types;
-use crate::errors::location::Location;
-use crate::to_error;
-use crate::{errors::compile::Res, to_suggestion};
+use crate::errors::compile::{CompileError, Res};
+use crate::errors::span::{FileId, Span};
+use crate::{to_error, to_suggestion};
+use core::str::SplitInclusive;
+extern crate alloc;
+use alloc::borrow::Cow;
+use alloc::collections::VecDeque;
 use end_state::end_current;
-use handle_state::handle_escape;
+use handle_state::{handle_escape, push_escaped_value};
+use identifiers::{
+    check_identifier_ucn, incomplete_ucn, is_identifier_continue, is_identifier_start,
+    universal_character_name,
+};
+use trigraphs::{replace_trigraphs, warn_ignored_trigraphs};
+use trivia::TriviaAttacher;
+use types::char_class::{run_len, CharClass};
 use types::escape_state::EscapeStatus;
 use types::lexing_data::LexingData;
+use types::lexing_options::LexingOptions;
 use types::lexing_state::{CommentStatus, LexingStatus, SymbolStatus};
-use types::tokens_types::Token;
+use types::source_line::SourceLine;
+use types::standard::Standard;
+use types::tokens_types::{StrValue, Token, TokenValue};
 
 #[macro_export]
 macro_rules! safe_parse_int {
@@ -44,11 +63,10 @@ macro_rules! safe_parse_int {
 #[allow(clippy::wildcard_enum_match_arm)]
 fn lex_char(
     ch: char,
-    location: &Location,
+    location: &Span,
     lex_data: &mut LexingData,
     lex_status: &mut LexingStatus,
     escape_status: &mut EscapeStatus,
-    eol: bool,
 ) {
     use LexingStatus::*;
     match (ch, lex_status, escape_status) {
@@ -56,28 +74,40 @@ fn lex_char(
         /* Inside comment */
         ('/', status @ Comment(CommentStatus::Star), _) => {
             *status = Comment(CommentStatus::False);
+            lex_data.push_comment(ch);
+            lex_data.end_comment(location);
         }
-        ('*', status @ Comment(CommentStatus::True), _) => {
+        ('*', status @ Comment(CommentStatus::True | CommentStatus::Star), _) => {
             *status = Comment(CommentStatus::Star);
+            lex_data.push_comment(ch);
         }
-        (_, Comment(CommentStatus::True), _) => (),
+        (_, Comment(CommentStatus::True), _) => lex_data.push_comment(ch),
         (_, status @ Comment(CommentStatus::Star), _) => {
             *status = Comment(CommentStatus::True);
+            lex_data.push_comment(ch);
         }
         /* Escaped character */
         (
             _,
-            status @ (Char(None) | Str(_)),
+            status @ (Char(..) | Str(..)),
             escape @ (EscapeStatus::Single | EscapeStatus::Sequence(_)),
         ) => {
-            if let Some(escaped) = handle_escape(ch, lex_data, escape, location) {
-                *escape = EscapeStatus::False;
-                match status {
-                    Char(None) => *status = Char(Some(escaped)),
-                    Str(val) => val.push(escaped),
-                    _ => panic!("this can't happen, see match above"),
+            // a character that ends a sequence isn't part of it
+            let terminates = escape.is_terminated_by(ch);
+            let numeric = escape.is_numeric();
+            let string = matches!(status, Str(..));
+            match handle_escape(ch, lex_data, escape, location, status.encoding(), string) {
+                Ok(Some(value)) => push_escaped_value(value, numeric, status, lex_data, location),
+                Ok(None) => (),
+                Err(()) => {
+                    if let Char(_, _, invalid) = status {
+                        *invalid = true;
+                    }
                 }
             }
+            if terminates {
+                lex_char(ch, location, lex_data, status, escape);
+            }
         }
 
         (_, _, EscapeStatus::Single | EscapeStatus::Sequence(_)) => {
@@ -86,45 +116,75 @@ fn lex_char(
         /* Create comment */
         ('*', status, _) if status.symbol().and_then(SymbolStatus::last) == Some('/') => {
             status.clear_last_symbol();
-            end_current(status, lex_data, location);
+            let slash = lex_data.span_before(location, 1);
+            end_current(status, lex_data, &slash);
             *status = Comment(CommentStatus::True);
+            lex_data.start_comment("/*", slash.to(*location));
         }
 
         /* Escape character */
-        ('\\', Char(None) | Str(_), escape) => *escape = EscapeStatus::Single,
-        ('\\', _, escape) if eol => *escape = EscapeStatus::Single,
-        ('\\', state, _) => lex_data.push_err(to_error!(
-            location,
-            "Escape characters are only authorised in strings or chars, not in '{}' context.",
-            state.repr()
-        )),
+        ('\\', Str(encoding, val @ None), escape) => {
+            // the content before the first escape sequence is spelled as is
+            let start = lex_data.source_end(lex_data.lexeme_start(), encoding.prefix().len() + 1);
+            let content = Span::new(location.file_id(), start, location.start());
+            *val = Some(StrValue::Text(Cow::Owned(lex_data.rewritten(&content))));
+            *escape = EscapeStatus::Single;
+        }
+        ('\\', Char(..) | Str(..), escape) => *escape = EscapeStatus::Single,
+        ('\\', state, _) => {
+            lex_data.push_err(to_error!(
+                location,
+                "Escape characters are only authorised in strings or chars, not in '{}' context.",
+                state.repr()
+            ));
+            end_current(state, lex_data, location);
+            lex_data.push_error_token(*location);
+        }
 
         /* Static strings and chars*/
         // open/close
-        ('\'', status @ Char(_), _) => end_current(status, lex_data, location),
-        ('\'', status, _) if !matches!(status, Str(_)) => {
-            end_current(status, lex_data, location);
-            *status = LexingStatus::Char(None);
+        ('\'', status @ Char(..), _) => end_current(status, lex_data, location),
+        ('\'', status, _) if !matches!(status, Str(..)) => {
+            let prefix = status.encoding_prefix();
+            if prefix.is_none() {
+                end_current(status, lex_data, location);
+            }
+            let encoding = prefix.unwrap_or_default();
+            lex_data.start_lexeme(encoding.prefix().len() + 1, location);
+            *status = LexingStatus::Char(encoding, vec![], false);
         }
-        ('\"', status @ Str(_), _) => {
+        ('\"', status @ Str(..), _) => {
             end_current(status, lex_data, location);
         }
-        ('\"', status, _) if !matches!(status, Char(_)) => {
-            end_current(status, lex_data, location);
-            *status = LexingStatus::Str(String::new());
+        ('\"', status, _) if !matches!(status, Char(..)) => {
+            let prefix = status.encoding_prefix();
+            if prefix.is_none() {
+                end_current(status, lex_data, location);
+            }
+            let encoding = prefix.unwrap_or_default();
+            lex_data.start_lexeme(encoding.prefix().len() + 1, location);
+            *status = LexingStatus::Str(encoding, None);
         }
         // middle
-        (_, Char(Some(_)), _) => lex_data.push_err(to_error!(
-            location,
-            "A char must contain only one character."
-        )),
-        (_, status @ Char(None), _) => *status = Char(Some(ch)),
-        (_, Str(val), _) => val.push(ch),
+        (_, Char(encoding, units, _), _) => units.extend(encoding.code_units(ch)),
+        (_, Str(encoding, val), _) => {
+            if let Some(value) = val {
+                value.push_str(ch.encode_utf8(&mut [0; 4]), *encoding);
+            }
+        }
 
         /* Operator symbols */
         ('/', status, _) if status.symbol().and_then(SymbolStatus::last) == Some('/') => {
             status.clear_last_symbol();
-            end_current(status, lex_data, location);
+            let slash = lex_data.span_before(location, 1);
+            end_current(status, lex_data, &slash);
+            if lex_data.standard() < Standard::C99 {
+                lex_data.push_err(to_error!(
+                    slash.to(*location),
+                    "Line comments ('//') are not allowed before C99. Use a block comment ('/* */') instead."
+                ));
+            }
+            lex_data.start_comment("//", slash.to(*location));
             lex_data.set_end_line();
         }
         ('.', Identifier(ident), _) if !ident.contains('.') && ident.is_number() => {
@@ -135,13 +195,17 @@ fn lex_char(
         }
         (
             '(' | ')' | '[' | ']' | '{' | '}' | '~' | '!' | '*' | '&' | '%' | '/' | '>' | '<' | '='
-            | '|' | '^' | ',' | '?' | ':' | ';' | '.',
+            | '|' | '^' | ',' | '?' | ':' | ';' | '.' | '#' | '+' | '-',
             status,
             _,
         ) => {
             if let Symbols(symbol_status) = status {
-                if let Some((size, symbol)) = symbol_status.push(ch) {
-                    lex_data.push_token(Token::from_symbol(symbol, size, location));
+                let start = lex_data.source_start(location.start(), symbol_status.len());
+                if let Some((lexeme, symbol)) = symbol_status.push(ch, lex_data.standard()) {
+                    let end = lex_data.source_end(start, lexeme.len());
+                    let span = Span::new(location.file_id(), start, end);
+                    let spelling = lex_data.borrow_source(&span, || lexeme.to_owned());
+                    lex_data.push_token(Token::from_symbol(symbol, spelling, span));
                 }
             } else {
                 end_current(status, lex_data, location);
@@ -155,15 +219,15 @@ fn lex_char(
         }
 
         // Whitespace: end of everyone
-        (_, Identifier(val), _) if ch.is_alphanumeric() || matches!(ch, '_' | '.' | '+' | '-') => {
+        (_, Identifier(val), _) if is_identifier_continue(ch) => {
             // dbg!("here", &val, ch);
             val.push(ch);
             // dbg!("there", &val);
         }
-        (_, status, _) if ch.is_alphanumeric() || matches!(ch, '_' | '.' | '+' | '-') => {
+        (_, status, _) if ch.is_ascii_digit() || is_identifier_start(ch) => {
             end_current(status, lex_data, location);
             // dbg!("blob", ch);
-            status.new_ident(ch);
+            status.new_ident(ch, lex_data.take_ident_buffer());
         }
         (_, status, _) => {
             lex_data.push_err(to_error!(
@@ -171,58 +235,360 @@ fn lex_char(
                 "Character '{ch}' not supported in context of a '{}'.",
                 status.repr()
             ));
+            end_current(status, lex_data, location);
+            lex_data.push_error_token(*location);
         }
     }
 }
 
-fn lex_line(
-    line: &str,
-    location: &mut Location,
+/// Lexes the universal character name `spelling` outside of a literal, as part
+/// of an identifier.
+fn lex_ucn(
+    spelling: &str,
+    value: u32,
+    location: &Span,
     lex_data: &mut LexingData,
     lex_status: &mut LexingStatus,
 ) {
-    lex_data.newline();
+    let start = !matches!(lex_status, LexingStatus::Identifier(_));
+    match check_identifier_ucn(value, start) {
+        Ok(_) => {
+            if let LexingStatus::Identifier(ident) = lex_status {
+                ident.push_str(spelling);
+            } else {
+                end_current(lex_status, lex_data, location);
+                lex_status.new_ident_ucn(spelling);
+            }
+        }
+        Err(reason) => {
+            lex_data.push_err(to_error!(
+                location,
+                "Invalid universal character name '{spelling}' in an identifier: {reason}."
+            ));
+            end_current(lex_status, lex_data, location);
+            lex_data.push_error_token(*location);
+        }
+    }
+}
+
+/// Lexes the header name at the start of `rest` if there is one (C 6.4.7), and
+/// returns its length.
+fn lex_header_name(
+    rest: &str,
+    line: &SourceLine,
+    idx: usize,
+    lex_data: &mut LexingData,
+    lex_status: &mut LexingStatus,
+) -> Option<usize> {
+    let close = rest.find('>')?;
+    let open = line.span(idx, 1);
+    end_current(lex_status, lex_data, &open);
+    if !lex_data.expects_header_name() {
+        return None;
+    }
+    let span = open.to(line.span(idx + close, 1));
+    let name = rest.get(1..close).unwrap_or_default();
+    let lexeme = lex_data.borrow_source(&span, || format!("<{name}>"));
+    let value = match &lexeme {
+        Cow::Borrowed(spelled) => {
+            Cow::Borrowed(spelled.get(1..spelled.len() - 1).unwrap_or_default())
+        }
+        Cow::Owned(_) => Cow::Owned(name.to_owned()),
+    };
+    lex_data.push_token(Token::from_header_name(value, lexeme, span));
+    Some(close + 1)
+}
+
+/// Lexes the characters at the start of `rest` that can't change the state, and
+/// returns their length.
+fn lex_run(rest: &str, lex_data: &mut LexingData, lex_status: &mut LexingStatus) -> usize {
+    match lex_status {
+        LexingStatus::Identifier(ident) => {
+            let len = run_len(rest, CharClass::Identifier);
+            ident.push_str(rest.get(..len).unwrap_or_default());
+            len
+        }
+        LexingStatus::StartOfLine | LexingStatus::Unset => run_len(rest, CharClass::Whitespace),
+        LexingStatus::Comment(CommentStatus::True) => {
+            let len = rest.find('*').unwrap_or(rest.len());
+            lex_data.push_comment_str(rest.get(..len).unwrap_or_default());
+            len
+        }
+        LexingStatus::Str(encoding, val) => {
+            let len = rest.find(['"', '\\']).unwrap_or(rest.len());
+            if let Some(value) = val {
+                value.push_str(rest.get(..len).unwrap_or_default(), *encoding);
+            }
+            len
+        }
+        LexingStatus::Symbols(_)
+        | LexingStatus::Char(..)
+        | LexingStatus::Comment(CommentStatus::False | CommentStatus::Star) => 0,
+    }
+}
+
+/// Lexes a line as bytes, UTF-8 being only decoded for non-ASCII bytes.
+#[allow(clippy::too_many_lines)]
+fn lex_line(line: &SourceLine, lex_data: &mut LexingData, lex_status: &mut LexingStatus) {
+    lex_data.newline(line.removed());
+    if *lex_status == LexingStatus::Comment(CommentStatus::True) {
+        lex_data.push_comment('\n');
+    }
     let mut escape_state = EscapeStatus::False;
-    let trimed = line.trim_end();
-    if trimed.is_empty() {
+    let text = line.text();
+    let trimed = text.trim_end();
+    let Some(last_ch) = trimed.chars().next_back() else {
         return;
-    }
-    let last = trimed.len() - 1;
-    for (idx, ch) in trimed.chars().enumerate() {
+    };
+    let last = trimed.len() - last_ch.len_utf8();
+    let last_span = line.span(last, last_ch.len_utf8());
+    let mut idx = 0;
+    while let Some(byte) = trimed.as_bytes().get(idx) {
+        let rest = trimed.get(idx..).unwrap_or_default();
+        if escape_state == EscapeStatus::False {
+            let run = lex_run(rest, lex_data, lex_status);
+            if run != 0 {
+                idx += run;
+                continue;
+            }
+        }
+        if *byte == b'\\' && escape_state == EscapeStatus::False && lex_status.accepts_identifier()
+        {
+            if let Some((len, value)) = universal_character_name(rest) {
+                let start = line.span(idx, 1).start();
+                let end = line.span(idx + len - 1, 1).end();
+                let location = Span::new(last_span.file_id(), start, end);
+                let spelling = rest.get(..len).unwrap_or_default();
+                lex_ucn(spelling, value, &location, lex_data, lex_status);
+                idx += len;
+                continue;
+            }
+            if let Some((len, digits)) = incomplete_ucn(rest) {
+                let start = line.span(idx, 1).start();
+                let end = line.span(idx + len - 1, 1).end();
+                let location = Span::new(last_span.file_id(), start, end);
+                lex_data.push_err(to_error!(
+                    location,
+                    "Incomplete universal character name '{}': it must have {digits} hexadecimal digits.",
+                    rest.get(..len).unwrap_or_default()
+                ));
+                end_current(lex_status, lex_data, &location);
+                lex_data.push_error_token(location);
+                idx += len;
+                continue;
+            }
+        }
+        if *byte == b'<' && escape_state == EscapeStatus::False && lex_status.ends_before_lt() {
+            if let Some(len) = lex_header_name(rest, line, idx, lex_data, lex_status) {
+                idx += len;
+                continue;
+            }
+        }
+        let ch = if byte.is_ascii() {
+            char::from(*byte)
+        } else {
+            rest.chars().next().expect("idx is at a char boundary")
+        };
         lex_char(
             ch,
-            location,
+            &line.span(idx, ch.len_utf8()),
             lex_data,
             lex_status,
             &mut escape_state,
-            idx == last,
         );
-        location.incr_col();
+        idx += ch.len_utf8();
         if lex_data.is_end_line() {
+            // rest of the line is a `//` comment
+            lex_data.push_comment_str(trimed.get(idx..).unwrap_or_default());
+            lex_data.end_comment(&last_span);
             break;
         }
     }
-    end_current(lex_status, lex_data, location);
-    if line.trim_end().ends_with('\\') {
-        if line.ends_with(char::is_whitespace) {
-            lex_data.push_err(to_suggestion!(
-                location,
-                "found white space after '\\' at EOL. Please remove the space."
+    let after_last = Span::point(last_span.file_id(), last_span.end());
+    match lex_status {
+        LexingStatus::Str(..) => {
+            let (_, literal) = lex_data.take_lexeme(&after_last);
+            lex_data.push_err(to_error!(
+                literal,
+                "Unterminated string literal: missing closing quote before the end of the line."
             ));
+            lex_data.push_error_token(literal);
+            *lex_status = LexingStatus::Unset;
         }
+        LexingStatus::Char(..) => {
+            let (_, literal) = lex_data.take_lexeme(&after_last);
+            lex_data.push_err(to_error!(
+                literal,
+                "Unterminated character constant: missing closing quote before the end of the line."
+            ));
+            lex_data.push_error_token(literal);
+            *lex_status = LexingStatus::Unset;
+        }
+        LexingStatus::Comment(_)
+        | LexingStatus::Identifier(_)
+        | LexingStatus::StartOfLine
+        | LexingStatus::Symbols(_)
+        | LexingStatus::Unset => end_current(lex_status, lex_data, &after_last),
+    }
+    if let LexingStatus::Comment(status @ (CommentStatus::True | CommentStatus::Star)) = lex_status
+    {
+        // block comments go on after the end of the line
+        *status = CommentStatus::True;
     } else {
+        lex_data.end_comment(&after_last);
         *lex_status = LexingStatus::default();
     }
 }
 
-pub fn lex_file(content: &str, location: &mut Location) -> Res<Vec<Token>> {
-    let mut lex_data = LexingData::default();
-    let mut lex_status = LexingStatus::default();
+/// Lexes the source one line at a time, when tokens are requested.
+///
+/// The errors of a line are returned before its tokens. With trivia, a token is
+/// only returned once the next one was lexed.
+pub struct Lexer<'src> {
+    file_id: FileId,
+    lex_data: LexingData<'src>,
+    lex_status: LexingStatus,
+    /// No token was lexed since the last new-line outside of a comment.
+    line_start: bool,
+    lines: SplitInclusive<'src, char>,
+    offset: usize,
+    pending: VecDeque<Result<Token<'src>, CompileError>>,
+    trigraphs: bool,
+    trivia: Option<TriviaAttacher<'src>>,
+}
 
-    for line in content.lines() {
-        lex_line(line, location, &mut lex_data, &mut lex_status);
-        location.new_line();
+impl<'src> Lexer<'src> {
+    /// Returns `raw_line` without its line terminator, and with its trigraphs
+    /// replaced if they are enabled.
+    fn physical_line(&mut self, raw_line: &'src str) -> SourceLine<'src> {
+        let offset = self.offset;
+        self.offset += raw_line.len();
+        let text = raw_line
+            .strip_suffix('\n')
+            .map_or(raw_line, |line| line.strip_suffix('\r').unwrap_or(line));
+        if self.trigraphs {
+            replace_trigraphs(text, self.file_id, offset, &mut self.lex_data)
+        } else {
+            warn_ignored_trigraphs(text, self.file_id, offset, &mut self.lex_data);
+            SourceLine::new(text, self.file_id, offset)
+        }
     }
 
-    Res::from((lex_data.take_tokens(), lex_data.take_errors()))
+    fn lex_next_line(&mut self) -> bool {
+        let Some(raw_line) = self.lines.next() else {
+            if let Some(start) = self.lex_data.take_comment_start() {
+                if let Some(trivia) = &mut self.trivia {
+                    trivia.add_unterminated_comment(&start);
+                }
+                self.lex_status = LexingStatus::default();
+                self.pending.push_back(Err(to_error!(
+                    start,
+                    "Unterminated comment: missing closing '*/' before the end of the file."
+                )));
+                return true;
+            }
+            let last = self.trivia.as_mut().and_then(TriviaAttacher::finish);
+            return last
+                .map(|token| self.pending.push_back(Ok(token)))
+                .is_some();
+        };
+        let mut line = self.physical_line(raw_line);
+        // translation phase 2: joins the lines ending with a backslash
+        while let Some(backslash) = line.text().trim_end().strip_suffix('\\').map(str::len) {
+            let Some(next_raw) = self.lines.next() else {
+                self.lex_data.push_err(to_error!(
+                    line.span(backslash, 1),
+                    "Stray '\\' at the end of the file: a line splice must be followed by a line."
+                ));
+                line.splice(SourceLine::new("", self.file_id, self.offset), backslash);
+                break;
+            };
+            if backslash + 1 < line.text().len() {
+                self.lex_data.push_err(to_suggestion!(
+                    line.span(line.text().len(), 0),
+                    "found white space after '\\' at EOL. Please remove the space."
+                ));
+            }
+            let next = self.physical_line(next_raw);
+            line.splice(next, backslash);
+        }
+        self.lex_data.set_line_start(self.line_start);
+        if let Some(trivia) = &mut self.trivia {
+            trivia.add_splices(&line.removed());
+        }
+        lex_line(&line, &mut self.lex_data, &mut self.lex_status);
+        self.pending
+            .extend(self.lex_data.take_errors().into_iter().map(Err));
+        let mut tokens = self.lex_data.take_tokens();
+        if self.line_start {
+            for token in &mut tokens {
+                token.set_first_on_line();
+                if !matches!(token.get_value(), TokenValue::Comment(_)) {
+                    self.line_start = false;
+                    break;
+                }
+            }
+        }
+        if !matches!(self.lex_status, LexingStatus::Comment(CommentStatus::True)) {
+            self.line_start = true;
+        }
+        if let Some(trivia) = &mut self.trivia {
+            self.pending.extend(
+                tokens
+                    .into_iter()
+                    .filter_map(|token| trivia.push(token))
+                    .map(Ok),
+            );
+        } else {
+            self.pending.extend(tokens.into_iter().map(Ok));
+        }
+        true
+    }
+
+    pub fn new(content: &'src str, file_id: FileId, options: &LexingOptions) -> Self {
+        Self {
+            file_id,
+            lex_data: LexingData::new(options, content),
+            lex_status: LexingStatus::default(),
+            line_start: true,
+            lines: content.split_inclusive('\n'),
+            offset: 0,
+            pending: VecDeque::new(),
+            trigraphs: options.trigraphs,
+            trivia: options
+                .trivia
+                .then(|| TriviaAttacher::new(content, file_id, options.keep_comments)),
+        }
+    }
+
+    pub fn peek(&mut self) -> Option<&Result<Token<'src>, CompileError>> {
+        while self.pending.is_empty() && self.lex_next_line() {}
+        self.pending.front()
+    }
+}
+
+#[allow(clippy::missing_trait_methods)]
+impl<'src> Iterator for Lexer<'src> {
+    type Item = Result<Token<'src>, CompileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && self.lex_next_line() {}
+        self.pending.pop_front()
+    }
+}
+
+pub fn lex_file<'src>(
+    content: &'src str,
+    file_id: FileId,
+    options: &LexingOptions,
+) -> Res<Vec<Token<'src>>> {
+    let mut tokens = vec![];
+    let mut errors = vec![];
+    for item in Lexer::new(content, file_id, options) {
+        match item {
+            Ok(token) => tokens.push(token),
+            Err(error) => errors.push(error),
+        }
+    }
+    Res::from((tokens, errors))
 }
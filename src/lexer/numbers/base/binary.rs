@@ -1,5 +1,5 @@
 use crate::errors::compile::CompileError;
-use crate::errors::location::Location;
+use crate::errors::span::Span;
 #[allow(clippy::wildcard_imports)]
 use crate::lexer::numbers::types::arch_types::*;
 use crate::lexer::numbers::types::{Number, NumberType, ERR_PREFIX};
@@ -8,7 +8,7 @@ use crate::{parse_int_from_radix, to_error};
 pub fn to_bin_value(
     literal: &str,
     nb_type: &NumberType,
-    location: &Location,
+    location: &Span,
 ) -> Result<Number, CompileError> {
     if literal.chars().all(|ch| matches!(ch, '0' | '1')) {
         parse_int_from_radix!(location,
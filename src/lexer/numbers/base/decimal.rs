@@ -1,5 +1,5 @@
 use crate::errors::compile::CompileError;
-use crate::errors::location::Location;
+use crate::errors::span::Span;
 #[allow(clippy::wildcard_imports)]
 use crate::lexer::numbers::types::arch_types::*;
 use crate::lexer::numbers::types::{Number, NumberType, ERR_PREFIX};
@@ -17,7 +17,7 @@ macro_rules! parse_number {
     };
 }
 
-fn parse_and_error<T>(literal: &str, location: &Location) -> Result<T, CompileError>
+fn parse_and_error<T>(literal: &str, location: &Span) -> Result<T, CompileError>
 where
     T: FromStr,
     <T as FromStr>::Err: Into<ParseFloatError>,
@@ -30,7 +30,7 @@ where
 pub fn to_decimal_value(
     literal: &str,
     nb_type: &NumberType,
-    location: &Location,
+    location: &Span,
 ) -> Result<Number, CompileError> {
     parse_number!(location,  nb_type, literal, Int Long LongLong UInt ULong ULongLong, Float Double )
 }
@@ -1,4 +1,4 @@
-use crate::errors::{compile::CompileError, location::Location};
+use crate::errors::{compile::CompileError, span::Span};
 #[allow(clippy::wildcard_imports)]
 use crate::lexer::numbers::types::arch_types::*;
 use crate::lexer::numbers::types::{Number, NumberType, ERR_PREFIX};
@@ -44,8 +44,8 @@ enum HexFloatParseState {
 trait FloatingPoint<T> {
     const MANTISSA_SIZE: u32;
     type Unsigned;
-    fn from_unsigned(val: T, location: &Location, warning: &mut Option<CompileError>) -> Self;
-    fn from_usize(val: usize, location: &Location, warning: &mut Option<CompileError>) -> Self;
+    fn from_unsigned(val: T, location: &Span, warning: &mut Option<CompileError>) -> Self;
+    fn from_usize(val: usize, location: &Span, warning: &mut Option<CompileError>) -> Self;
 }
 
 macro_rules! impl_floating_point {
@@ -58,7 +58,7 @@ macro_rules! impl_floating_point {
 
             fn from_unsigned(
                 val: Self::Unsigned,
-                location: &Location,
+                location: &Span,
                 warning: &mut Option<CompileError>,
             ) -> Self {
                 if val >= (2 as Self::Unsigned).pow(Self::MANTISSA_SIZE) {
@@ -72,7 +72,7 @@ macro_rules! impl_floating_point {
 
             fn from_usize(
                 val: usize,
-                location: &Location,
+                location: &Span,
                 warning: &mut Option<CompileError>,
             ) -> Self {
                 if val >= 2usize.pow(Self::MANTISSA_SIZE) {
@@ -137,7 +137,7 @@ fn hex_char_to_int(ch: char) -> u8 {
     }
 }
 
-fn get_hex_float_state(literal: &str, location: &Location) -> Result<HexFloatParse, CompileError> {
+fn get_hex_float_state(literal: &str, location: &Span) -> Result<HexFloatParse, CompileError> {
     let mut float_parse = HexFloatParse::default();
     for ch in literal.chars() {
         match ch {
@@ -163,7 +163,7 @@ fn get_hex_float_state(literal: &str, location: &Location) -> Result<HexFloatPar
 pub fn to_hex_value(
     literal: &str,
     nb_type: &NumberType,
-    location: &Location,
+    location: &Span,
 ) -> Result<Number, CompileError> {
     let float_parse = get_hex_float_state(literal, location)?;
     if nb_type.is_int() {
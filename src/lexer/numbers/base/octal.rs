@@ -1,5 +1,5 @@
 use crate::errors::compile::CompileError;
-use crate::errors::location::Location;
+use crate::errors::span::Span;
 #[allow(clippy::wildcard_imports)]
 use crate::lexer::numbers::types::arch_types::*;
 use crate::lexer::numbers::types::NumberType;
@@ -11,7 +11,7 @@ use crate::to_error;
 pub fn to_oct_value(
     literal: &str,
     nb_type: &NumberType,
-    location: &Location,
+    location: &Span,
 ) -> Result<Number, CompileError> {
     if literal.chars().all(|ch| matches!(ch, '0'..='7')) {
         parse_int_from_radix!(
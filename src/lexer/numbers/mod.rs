@@ -1,6 +1,6 @@
 mod base;
 pub mod types;
-use crate::errors::{compile::CompileError, location::Location};
+use crate::errors::{compile::CompileError, span::Span};
 use crate::to_error;
 use base::{binary, decimal, hexadecimal, octal};
 use core::str;
@@ -13,11 +13,12 @@ use types::{Base, NumberType, ERR_PREFIX};
 
 use super::types::lexing_data::LexingData;
 use super::types::lexing_state::Ident;
+use super::types::standard::Standard;
 
 pub fn literal_to_number(
     lex_data: &mut LexingData,
     literal: &Ident,
-    location: &Location,
+    location: &Span,
 ) -> Option<Number> {
     if literal.is_empty() || !literal.is_number() {
         return None;
@@ -30,16 +31,23 @@ pub fn literal_to_number(
     }
 
     match literal_to_number_err(literal.value(), location) {
-        Ok(nb) => Some(nb),
-        Err(mut error) => {
-            error.specify_length(literal.len() - 1);
+        Ok(nb) => {
+            if literal.value().starts_with("0b") && lex_data.standard() < Standard::C23 {
+                lex_data.push_err(to_error!(
+                    location,
+                    "{ERR_PREFIX}binary constants are not allowed before C23."
+                ));
+            }
+            Some(nb)
+        }
+        Err(error) => {
             lex_data.push_err(error);
             None
         }
     }
 }
 
-fn literal_to_number_err(literal: &str, location: &Location) -> Result<Number, CompileError> {
+fn literal_to_number_err(literal: &str, location: &Span) -> Result<Number, CompileError> {
     let nb_type = get_number_type(literal, location)?;
     let base = get_base(literal, &nb_type, location)?;
     let value = str::from_utf8(
@@ -85,11 +93,7 @@ fn check_with_base(literal: &str, base: &Base) -> Option<char> {
     }
 }
 
-fn get_base(
-    literal: &str,
-    nb_type: &NumberType,
-    location: &Location,
-) -> Result<Base, CompileError> {
+fn get_base(literal: &str, nb_type: &NumberType, location: &Span) -> Result<Base, CompileError> {
     let mut chars = literal.chars();
     let first = chars.next().expect("len >= 1");
     let second = chars.next().expect("len >= 2");
@@ -125,7 +129,7 @@ fn get_base(
     }
 }
 
-fn get_number_type(literal: &str, location: &Location) -> Result<NumberType, CompileError> {
+fn get_number_type(literal: &str, location: &Span) -> Result<NumberType, CompileError> {
     // TODO: automatic conversion to bigger int if too large, whatever the suffix
     let is_hex = literal.starts_with("0x");
     /* literal characteristics */
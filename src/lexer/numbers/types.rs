@@ -26,7 +26,7 @@ pub mod arch_types {
 
 macro_rules! define_nb_types {
     ($($t:ident)*) => {
-        #[derive(Debug, PartialEq)]
+        #[derive(Debug, PartialEq, Clone)]
         pub enum Number {
             $($t($t),)*
         }
@@ -83,6 +83,27 @@ impl fmt::Display for NumberType {
     }
 }
 
+#[allow(clippy::min_ident_chars)]
+#[allow(
+    clippy::use_debug,
+    reason = "Debug always prints the decimal point of floats"
+)]
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(val) => write!(f, "{val}"),
+            Self::Long(val) => write!(f, "{val}L"),
+            Self::LongLong(val) => write!(f, "{val}LL"),
+            Self::UInt(val) => write!(f, "{val}U"),
+            Self::ULong(val) => write!(f, "{val}UL"),
+            Self::ULongLong(val) => write!(f, "{val}ULL"),
+            Self::Float(val) => write!(f, "{val:?}f"),
+            Self::Double(val) => write!(f, "{val:?}"),
+            Self::LongDouble(val) => write!(f, "{val:?}L"),
+        }
+    }
+}
+
 pub enum Base {
     Binary,
     Decimal,
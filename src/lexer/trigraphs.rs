@@ -0,0 +1,82 @@
+use super::types::lexing_data::LexingData;
+use super::types::source_line::SourceLine;
+use crate::errors::span::{FileId, Span};
+use crate::to_warning;
+
+pub const fn trigraph_replacement(ch: char) -> Option<char> {
+    match ch {
+        '=' => Some('#'),
+        '(' => Some('['),
+        '/' => Some('\\'),
+        ')' => Some(']'),
+        '\'' => Some('^'),
+        '<' => Some('{'),
+        '!' => Some('|'),
+        '>' => Some('}'),
+        '-' => Some('~'),
+        _ => None,
+    }
+}
+
+/// Replaces the trigraphs of `line` (translation phase 1), with a warning for
+/// each of them.
+pub fn replace_trigraphs<'src>(
+    line: &'src str,
+    file_id: FileId,
+    offset: usize,
+    lex_data: &mut LexingData,
+) -> SourceLine<'src> {
+    if !line.contains("??") {
+        return SourceLine::new(line, file_id, offset);
+    }
+    let mut replaced = String::with_capacity(line.len());
+    let mut trigraphs = vec![];
+    let mut rest = line;
+    while let Some(ch) = rest.chars().next() {
+        let trigraph = rest
+            .strip_prefix("??")
+            .and_then(|after| after.chars().next())
+            .and_then(|last| trigraph_replacement(last).map(|replacement| (last, replacement)));
+        if let Some((last, replacement)) = trigraph {
+            let start = offset + line.len() - rest.len();
+            lex_data.push_err(to_warning!(
+                Span::new(file_id, start, start + 3),
+                "Trigraph '??{last}' replaced by '{replacement}'."
+            ));
+            trigraphs.push(replaced.len());
+            replaced.push(replacement);
+            rest = rest.get(3..).unwrap_or_default();
+        } else {
+            replaced.push(ch);
+            rest = rest.get(ch.len_utf8()..).unwrap_or_default();
+        }
+    }
+    SourceLine::with_trigraphs(replaced, file_id, offset, trigraphs)
+}
+
+/// Warns about the trigraphs of `line`, that are kept as trigraphs are
+/// disabled.
+pub fn warn_ignored_trigraphs(
+    line: &str,
+    file_id: FileId,
+    offset: usize,
+    lex_data: &mut LexingData,
+) {
+    let mut start = 0;
+    while let Some(found) = line.get(start..).and_then(|rest| rest.find("??")) {
+        let idx = start + found;
+        let last = line
+            .get(idx + 2..)
+            .and_then(|after| after.chars().next())
+            .filter(|last| trigraph_replacement(*last).is_some());
+        if let Some(trigraph) = last {
+            lex_data.push_err(to_warning!(
+                Span::new(file_id, offset + idx, offset + idx + 3),
+                "Trigraph '??{trigraph}' ignored: use '-trigraphs' to replace it."
+            ));
+            start = idx + 3;
+        } else {
+            start = idx + 1;
+        }
+    }
+}
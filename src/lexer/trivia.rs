@@ -0,0 +1,142 @@
+use super::types::source_line::Removed;
+use super::types::tokens_types::{Token, TokenValue};
+use super::types::trivia::{Trivia, TriviaKind};
+use crate::errors::span::{FileId, Span};
+use core::mem;
+
+fn whitespace_before_newline(trivia: &mut Trivia, content: &str) -> Option<Trivia> {
+    if trivia.get_kind() != TriviaKind::Whitespace {
+        return None;
+    }
+    let span = *trivia.get_location();
+    let before = span.text(content).find(['\r', '\n'])?;
+    if before == 0 {
+        return None;
+    }
+    let newline = span.start() + before;
+    *trivia = Trivia::new(
+        TriviaKind::Whitespace,
+        Span::new(span.file_id(), newline, span.end()),
+    );
+    Some(Trivia::new(
+        TriviaKind::Whitespace,
+        Span::new(span.file_id(), span.start(), newline),
+    ))
+}
+
+/// Attaches the trivia between the tokens to them: up to the first line
+/// terminator after a token, it is trailing trivia of that token, and leading
+/// trivia of the next one after.
+pub struct TriviaAttacher<'src> {
+    content: &'src str,
+    file_id: FileId,
+    keep_comments: bool,
+    last: Option<Token<'src>>,
+    last_end: usize,
+    pieces: Vec<Trivia>,
+}
+
+impl<'src> TriviaAttacher<'src> {
+    pub fn add_splices(&mut self, removed: &[Removed]) {
+        self.pieces
+            .extend(removed.iter().filter(|range| !range.trigraph).map(|range| {
+                Trivia::new(
+                    TriviaKind::LineSplice,
+                    Span::new(self.file_id, range.start, range.end),
+                )
+            }));
+    }
+
+    pub fn add_unterminated_comment(&mut self, start: &Span) {
+        self.pieces.push(Trivia::new(
+            TriviaKind::BlockComment,
+            Span::new(self.file_id, start.start(), self.content.len()),
+        ));
+    }
+
+    pub fn finish(&mut self) -> Option<Token<'src>> {
+        let trivia = self.trivia_before(self.content.len());
+        let mut last = self.last.take()?;
+        last.trailing_trivia_mut().extend(trivia);
+        Some(last)
+    }
+
+    pub const fn new(content: &'src str, file_id: FileId, keep_comments: bool) -> Self {
+        Self {
+            content,
+            file_id,
+            keep_comments,
+            last: None,
+            last_end: 0,
+            pieces: vec![],
+        }
+    }
+
+    /// Attaches the trivia before `token`, and returns the previous token, whose
+    /// trailing trivia is now known.
+    pub fn push(&mut self, mut token: Token<'src>) -> Option<Token<'src>> {
+        let location = *token.get_location();
+        if let TokenValue::Comment(comment) = token.get_value() {
+            if !self.keep_comments {
+                let kind = if comment.starts_with("//") {
+                    TriviaKind::LineComment
+                } else {
+                    TriviaKind::BlockComment
+                };
+                self.pieces.push(Trivia::new(kind, location));
+                return None;
+            }
+        }
+        let mut trivia = self.trivia_before(location.start());
+        if let Some(last) = &mut self.last {
+            let newline = trivia.iter().position(|piece| {
+                piece
+                    .get_location()
+                    .text(self.content)
+                    .contains(['\r', '\n'])
+            });
+            let split = newline.unwrap_or(trivia.len());
+            let mut leading = trivia.split_off(split);
+            if let Some(first) = leading.first_mut() {
+                if let Some(before) = whitespace_before_newline(first, self.content) {
+                    trivia.push(before);
+                }
+            }
+            last.trailing_trivia_mut().extend(trivia);
+            trivia = leading;
+        }
+        token.leading_trivia_mut().extend(trivia);
+        self.last_end = self.last_end.max(location.end());
+        self.last.replace(token)
+    }
+
+    fn trivia_before(&mut self, end: usize) -> Vec<Trivia> {
+        let (mut before, after): (Vec<_>, Vec<_>) = mem::take(&mut self.pieces)
+            .into_iter()
+            .partition(|piece| piece.get_location().start() < end);
+        self.pieces = after;
+        before.sort_by_key(|piece| piece.get_location().start());
+        let mut trivia = vec![];
+        let mut offset = self.last_end;
+        for piece in before {
+            let location = *piece.get_location();
+            // line splices inside a token or a comment are part of it
+            if location.start() < offset {
+                continue;
+            }
+            if offset < location.start() {
+                trivia.push(self.whitespace(offset, location.start()));
+            }
+            trivia.push(piece);
+            offset = location.end();
+        }
+        if offset < end {
+            trivia.push(self.whitespace(offset, end));
+        }
+        trivia
+    }
+
+    const fn whitespace(&self, start: usize, end: usize) -> Trivia {
+        Trivia::new(TriviaKind::Whitespace, Span::new(self.file_id, start, end))
+    }
+}
@@ -0,0 +1,39 @@
+/// Class of an ASCII character, to lex runs of characters at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Identifier,
+    Whitespace,
+    Other,
+}
+
+const fn classify(byte: u8) -> CharClass {
+    match byte {
+        b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' => CharClass::Identifier,
+        b' ' | b'\t' | b'\n' | b'\x0B' | b'\x0C' | b'\r' => CharClass::Whitespace,
+        _ => CharClass::Other,
+    }
+}
+
+#[allow(clippy::as_conversions, clippy::indexing_slicing)]
+const CHAR_CLASSES: [CharClass; 128] = {
+    let mut classes = [CharClass::Other; 128];
+    let mut byte = 0u8;
+    while byte < 128 {
+        classes[byte as usize] = classify(byte);
+        byte += 1;
+    }
+    classes
+};
+
+pub fn char_class(byte: u8) -> CharClass {
+    CHAR_CLASSES
+        .get(usize::from(byte))
+        .copied()
+        .unwrap_or(CharClass::Other)
+}
+
+pub fn run_len(text: &str, class: CharClass) -> usize {
+    text.bytes()
+        .position(|byte| char_class(byte) != class)
+        .unwrap_or(text.len())
+}
@@ -7,6 +7,14 @@ pub enum EscapeSequence {
 }
 
 impl EscapeSequence {
+    pub const fn accepts(&self, ch: char) -> bool {
+        if self.is_octal() {
+            ch.is_ascii_octdigit()
+        } else {
+            ch.is_ascii_hexdigit()
+        }
+    }
+
     pub const fn is_hexa(&self) -> bool {
         matches!(self, Self::Hexadecimal(_) | Self::ShortUnicode(_))
     }
@@ -19,7 +27,8 @@ impl EscapeSequence {
         match self {
             Self::ShortUnicode(_) => 4,
             Self::Unicode(_) => 8,
-            Self::Hexadecimal(_) => 2,
+            // hexadecimal sequences end at the first non hexadecimal digit
+            Self::Hexadecimal(_) => usize::MAX,
             Self::Octal(_) => 3,
         }
     }
@@ -67,3 +76,17 @@ pub enum EscapeStatus {
     Single,
     False,
 }
+
+impl EscapeStatus {
+    /// Octal and hexadecimal sequences give a code unit, not a character.
+    pub const fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Self::Sequence(EscapeSequence::Hexadecimal(_) | EscapeSequence::Octal(_))
+        )
+    }
+
+    pub const fn is_terminated_by(&self, ch: char) -> bool {
+        matches!(self, Self::Sequence(sequence) if !sequence.accepts(ch))
+    }
+}
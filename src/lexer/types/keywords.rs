@@ -1,6 +1,17 @@
+use super::standard::Standard;
+
+macro_rules! some_or_none {
+    () => {
+        None
+    };
+    ($value:expr) => {
+        Some($value)
+    };
+}
+
 macro_rules! impl_keywords {
-    ($($pascal:ident $ktype:ident $str:expr ,)*) => {
-        #[derive(Debug, PartialEq, Eq)]
+    ($($pascal:ident $ktype:ident $standard:ident $dialect:ident $str:expr $(=> $spelling:expr)? ,)*) => {
+        #[derive(Debug, PartialEq, Eq, Clone)]
         pub enum Keyword {
             $($pascal,)*
         }
@@ -17,6 +28,25 @@ macro_rules! impl_keywords {
                     $(Self::$pascal => KeywordType::$ktype,)*
                 }
             }
+
+            pub const fn dialect(&self) -> Dialect {
+                match self {
+                    $(Self::$pascal => Dialect::$dialect,)*
+                }
+            }
+
+            pub const fn standard(&self) -> Standard {
+                match self {
+                    $(Self::$pascal => Standard::$standard,)*
+                }
+            }
+
+            /// Returns the C23 spelling of a keyword deprecated in C23.
+            pub const fn c23_spelling(&self) -> Option<&'static str> {
+                match self {
+                    $(Self::$pascal => some_or_none!($($spelling)?),)*
+                }
+            }
         }
 
         impl TryFrom<&str> for Keyword {
@@ -29,74 +59,120 @@ macro_rules! impl_keywords {
             }
         }
 
-        pub const KEYWORDS: [&str; 59] = [$($str,)*];
+        pub const KEYWORDS: [&str; 92] = [$($str,)*];
     };
 }
 
 impl_keywords!(
-    Alignof Operator "alignof",
-    Alignas Storage "alignas",
-    Auto Storage "auto",
-    Bool Type "bool",
-    Break Control "break",
-    Case Control "case",
-    Char Type "char",
-    Const Storage "const",
-    Constexpr Storage "constexpr",
-    Continue Control "continue",
-    Default Control "default",
-    Do Control "do",
-    Double Type "double",
-    Else Control "else",
-    Enum Type "enum",
-    Extern Storage "extern",
-    False Literal "false",
-    Float Type "float",
-    For Control "for",
-    Goto Control "goto",
-    If Control "if",
-    Inline Storage "inline",
-    Int Type "int",
-    Long Type "long",
-    Nullptr Literal "nullptr",
-    Register Storage "register",
-    Restrict Storage "restrict",
-    Return Control "return",
-    Short Type "short",
-    Signed Type "signed",
-    Sizeof Operator "sizeof",
-    Static Storage "static",
-    StaticAssert Control "static_assert",
-    Struct Type "struct",
-    Switch Control "switch",
-    ThreadLocal Storage "thread_local",
-    True Literal "true",
-    Typedef Storage "typedef",
-    Typeof Operator "typeof",
-    TypeofUnqual Operator "typeof_unqual",
-    Union Type "union",
-    Unsigned Type "unsigned",
-    Void Type "void",
-    Volatile Storage "volatile",
-    While Control "while",
-    UAlignas Storage "_Alignas", // depr C23
-    UAlignof Operator "_Alignof", // depr C23
-    UAtomic Storage "_Atomic",
-    UBitInt Type "_BitInt",
-    UBool Type "_Bool", // depr C23
-    UComplex Type "_Complex",
-    UDecimal128 Type "_Decimal128",
-    UDecimal32 Type "_Decimal32",
-    UDecimal64 Type "_Decimal64",
-    UGeneric Operator "_Generic",
-    UImaginary Type "_Imaginary",
-    UNoreturn Storage "_Noreturn", // depr C23
-    UStaticAssert Control "_Static_assert", // depr C23
-    UThreadLocal Storage "_Thread_local", // depr C23
+    Alignof Operator C23 Iso "alignof",
+    Alignas Storage C23 Iso "alignas",
+    Auto Storage C89 Iso "auto",
+    Bool Type C23 Iso "bool",
+    Break Control C89 Iso "break",
+    Case Control C89 Iso "case",
+    Char Type C89 Iso "char",
+    Const Storage C89 Iso "const",
+    Constexpr Storage C23 Iso "constexpr",
+    Continue Control C89 Iso "continue",
+    Default Control C89 Iso "default",
+    Do Control C89 Iso "do",
+    Double Type C89 Iso "double",
+    Else Control C89 Iso "else",
+    Enum Type C89 Iso "enum",
+    Extern Storage C89 Iso "extern",
+    False Literal C23 Iso "false",
+    Float Type C89 Iso "float",
+    For Control C89 Iso "for",
+    Goto Control C89 Iso "goto",
+    If Control C89 Iso "if",
+    Inline Storage C99 Iso "inline",
+    Int Type C89 Iso "int",
+    Long Type C89 Iso "long",
+    Nullptr Literal C23 Iso "nullptr",
+    Register Storage C89 Iso "register",
+    Restrict Storage C99 Iso "restrict",
+    Return Control C89 Iso "return",
+    Short Type C89 Iso "short",
+    Signed Type C89 Iso "signed",
+    Sizeof Operator C89 Iso "sizeof",
+    Static Storage C89 Iso "static",
+    StaticAssert Control C23 Iso "static_assert",
+    Struct Type C89 Iso "struct",
+    Switch Control C89 Iso "switch",
+    ThreadLocal Storage C23 Iso "thread_local",
+    True Literal C23 Iso "true",
+    Typedef Storage C89 Iso "typedef",
+    Typeof Operator C23 Iso "typeof",
+    TypeofUnqual Operator C23 Iso "typeof_unqual",
+    Union Type C89 Iso "union",
+    Unsigned Type C89 Iso "unsigned",
+    Void Type C89 Iso "void",
+    Volatile Storage C89 Iso "volatile",
+    While Control C89 Iso "while",
+    UAlignas Storage C11 Iso "_Alignas" => "alignas",
+    UAlignof Operator C11 Iso "_Alignof" => "alignof",
+    UAtomic Storage C11 Iso "_Atomic",
+    UBitInt Type C23 Iso "_BitInt",
+    UBool Type C99 Iso "_Bool" => "bool",
+    UComplex Type C99 Iso "_Complex",
+    UDecimal128 Type C23 Iso "_Decimal128",
+    UDecimal32 Type C23 Iso "_Decimal32",
+    UDecimal64 Type C23 Iso "_Decimal64",
+    UGeneric Operator C11 Iso "_Generic",
+    UImaginary Type C99 Iso "_Imaginary",
+    UNoreturn Storage C11 Iso "_Noreturn" => "[[noreturn]]",
+    UStaticAssert Control C11 Iso "_Static_assert" => "static_assert",
+    UThreadLocal Storage C11 Iso "_Thread_local" => "thread_local",
+    Asm Control C89 Gnu "asm",
+    UUAlignof Operator C89 GnuMsvc "__alignof",
+    UUAlignofUU Operator C89 Gnu "__alignof__",
+    UUAsm Control C89 GnuMsvc "__asm",
+    UUAsmUU Control C89 Gnu "__asm__",
+    UUAttribute Attribute C89 Gnu "__attribute",
+    UUAttributeUU Attribute C89 Gnu "__attribute__",
+    UUAutoType Type C89 Gnu "__auto_type",
+    UUBuiltinChooseExpr Operator C89 Gnu "__builtin_choose_expr",
+    UUBuiltinOffsetof Operator C89 Gnu "__builtin_offsetof",
+    UUBuiltinTypesCompatibleP Operator C89 Gnu "__builtin_types_compatible_p",
+    UUBuiltinVaArg Operator C89 Gnu "__builtin_va_arg",
+    UUBuiltinVaList Type C89 Gnu "__builtin_va_list",
+    UUCdecl Attribute C89 Msvc "__cdecl",
+    UUDeclspec Attribute C89 Msvc "__declspec",
+    UUExtensionUU Operator C89 Gnu "__extension__",
+    UUFastcall Attribute C89 Msvc "__fastcall",
+    UUForceinline Storage C89 Msvc "__forceinline",
+    UUImagUU Operator C89 Gnu "__imag__",
+    UUInline Storage C89 GnuMsvc "__inline",
+    UUInlineUU Storage C89 Gnu "__inline__",
+    UUInt128 Type C89 Gnu "__int128",
+    UUInt16 Type C89 Msvc "__int16",
+    UUInt32 Type C89 Msvc "__int32",
+    UUInt64 Type C89 Msvc "__int64",
+    UUInt8 Type C89 Msvc "__int8",
+    UURealUU Operator C89 Gnu "__real__",
+    UURestrict Storage C89 GnuMsvc "__restrict",
+    UURestrictUU Storage C89 Gnu "__restrict__",
+    UUStdcall Attribute C89 Msvc "__stdcall",
+    UUTypeof Operator C89 Gnu "__typeof",
+    UUTypeofUU Operator C89 Gnu "__typeof__",
+    UUVolatileUU Storage C89 Gnu "__volatile__",
 );
 
+/// Dialects of C that reserve a keyword. Only the `__builtin_*` taking a type
+/// or a member name are keywords.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Dialect {
+    Gnu,
+    GnuMsvc,
+    /// Always reserved from its standard.
+    Iso,
+    Msvc,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum KeywordType {
+    /// Attribute or calling convention specifier.
+    Attribute,
     Type,
     Storage,
     Control,
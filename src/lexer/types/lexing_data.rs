@@ -1,17 +1,198 @@
-use super::tokens_types::Token;
-use crate::errors::compile::CompileError;
+use super::keywords::Keyword;
+use super::lexing_options::{Extensions, LexingOptions};
+use super::source_line::Removed;
+use super::standard::Standard;
+use super::tokens_types::{Symbol, Token, TokenValue};
+use crate::errors::{compile::CompileError, span::Span};
+use crate::lexer::trigraphs::trigraph_replacement;
 use core::mem;
+extern crate alloc;
+use alloc::borrow::Cow;
 
 #[derive(Debug, Default)]
-pub struct LexingData {
+pub struct LexingData<'src> {
     errors: Vec<CompileError>,
-    tokens: Vec<Token>,
-    failed: bool,
+    tokens: Vec<Token<'src>>,
     end_line: bool,
+    extensions: Extensions,
+    keep_comments: bool,
+    comment: Option<(String, Span)>,
+    /// Opening of the comment being lexed, even if comments aren't kept.
+    comment_start: Option<Span>,
+    ident_buffer: String,
+    lexeme_start: usize,
+    /// The line being lexed starts after a new-line outside of a comment.
+    line_start: bool,
+    /// Trigraph ends and line splices of the line being lexed.
+    removed: Vec<Removed>,
+    source: &'src str,
+    standard: Standard,
 }
 
-impl LexingData {
-    pub fn pop_token(&mut self) -> Option<Token> {
+impl<'src> LexingData<'src> {
+    pub fn new(options: &LexingOptions, source: &'src str) -> Self {
+        Self {
+            extensions: options.extensions,
+            // the comments are trivia otherwise
+            keep_comments: options.keep_comments || options.trivia,
+            source,
+            standard: options.standard,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the text lexed at `span`, borrowed from the source unless it
+    /// contains line splices or trigraphs, in which case `text` builds it.
+    pub fn borrow_source<F>(&self, span: &Span, text: F) -> Cow<'src, str>
+    where
+        F: FnOnce() -> String,
+    {
+        let rewritten = self
+            .removed
+            .iter()
+            .any(|removed| removed.start < span.end() && span.start() < removed.end);
+        match self.source.get(span.start()..span.end()) {
+            Some(spelled) if !rewritten => Cow::Borrowed(spelled),
+            Some(_) | None => Cow::Owned(text()),
+        }
+    }
+
+    /// Returns the source at `span` without its line splices and trigraphs.
+    pub fn rewritten(&self, span: &Span) -> String {
+        let mut text = String::with_capacity(span.end() - span.start());
+        let mut offset = span.start();
+        for removed in self
+            .removed
+            .iter()
+            .filter(|removed| removed.start < span.end() && span.start() < removed.end)
+        {
+            let kept = self
+                .source
+                .get(offset..removed.start.max(offset))
+                .unwrap_or_default();
+            if removed.trigraph {
+                // `kept` ends with the first '?' of the trigraph, whose last
+                // character ends the range
+                let replacement = self
+                    .source
+                    .get(..removed.end)
+                    .and_then(|before| before.chars().next_back())
+                    .and_then(trigraph_replacement);
+                text.push_str(kept.strip_suffix('?').unwrap_or(kept));
+                text.extend(replacement);
+            } else {
+                text.push_str(kept);
+            }
+            offset = offset.max(removed.end);
+        }
+        text.push_str(self.source.get(offset..span.end()).unwrap_or_default());
+        text
+    }
+
+    /// Returns the source offset of the start of the `len` lexed bytes ending at
+    /// `end`.
+    pub fn source_start(&self, end: usize, len: usize) -> usize {
+        if self.removed.is_empty() {
+            return end.saturating_sub(len);
+        }
+        let mut start = end;
+        for _ in 0..len {
+            // back-to-back splices are adjacent ranges
+            while let Some(removed) = self.removed.iter().find(|removed| removed.end == start) {
+                start = removed.start;
+            }
+            start = start.saturating_sub(1);
+        }
+        start
+    }
+
+    /// Returns the source offset of the end of the `len` lexed bytes starting at
+    /// `start`.
+    pub fn source_end(&self, start: usize, len: usize) -> usize {
+        if self.removed.is_empty() {
+            return start + len;
+        }
+        let mut end = start;
+        for idx in 0..len {
+            if idx != 0 {
+                while let Some(removed) = self.removed.iter().find(|removed| removed.start == end) {
+                    end = removed.end;
+                }
+            }
+            end += 1;
+        }
+        // the end of a trigraph belongs to its last character
+        self.removed
+            .iter()
+            .find(|removed| removed.trigraph && removed.start == end)
+            .map_or(end, |removed| removed.end)
+    }
+
+    pub fn span_before(&self, end: &Span, len: usize) -> Span {
+        let start = self.source_start(end.start(), len);
+        Span::new(end.file_id(), start, self.source_end(start, len))
+    }
+
+    pub fn start_comment(&mut self, start: &str, location: Span) {
+        self.comment_start = Some(location);
+        if self.keep_comments {
+            self.comment = Some((start.to_owned(), location));
+        }
+    }
+
+    pub fn push_comment(&mut self, ch: char) {
+        if let Some((comment, _)) = &mut self.comment {
+            comment.push(ch);
+        }
+    }
+
+    pub fn push_comment_str(&mut self, text: &str) {
+        if let Some((comment, _)) = &mut self.comment {
+            comment.push_str(text);
+        }
+    }
+
+    pub fn end_comment(&mut self, location: &Span) {
+        self.comment_start = None;
+        if let Some((comment, start)) = self.comment.take() {
+            self.tokens.push(Token::from_value(
+                TokenValue::Comment(comment),
+                start.to(*location),
+            ));
+        }
+    }
+
+    pub fn take_comment_start(&mut self) -> Option<Span> {
+        self.comment = None;
+        self.comment_start.take()
+    }
+
+    /// Starts a string or char, whose prefix and quote of length `len` end at
+    /// `location`.
+    pub fn start_lexeme(&mut self, len: usize, location: &Span) {
+        self.lexeme_start = self.source_start(location.end(), len);
+    }
+
+    pub const fn lexeme_start(&self) -> usize {
+        self.lexeme_start
+    }
+
+    pub fn take_lexeme(&self, location: &Span) -> (Cow<'src, str>, Span) {
+        let span = Span::new(location.file_id(), self.lexeme_start, location.end());
+        (self.borrow_source(&span, || self.rewritten(&span)), span)
+    }
+
+    pub fn take_ident_buffer(&mut self) -> String {
+        let mut buffer = mem::take(&mut self.ident_buffer);
+        buffer.clear();
+        buffer
+    }
+
+    pub fn recycle_ident_buffer(&mut self, buffer: String) {
+        self.ident_buffer = buffer;
+    }
+
+    pub fn pop_token(&mut self) -> Option<Token<'src>> {
         self.tokens.pop()
     }
 
@@ -19,32 +200,68 @@ impl LexingData {
         mem::take(&mut self.errors)
     }
 
-    pub fn take_tokens(&mut self) -> Vec<Token> {
+    pub fn take_tokens(&mut self) -> Vec<Token<'src>> {
         mem::take(&mut self.tokens)
     }
 
     pub fn push_err(&mut self, error: CompileError) {
-        let is_error = error.is_error();
         self.errors.push(error);
-        if is_error {
-            self.failed = true;
-        }
     }
 
-    pub fn push_token(&mut self, token: Token) {
+    /// Pushes an error token, so that lexing goes on after it.
+    pub fn push_error_token(&mut self, location: Span) {
+        let text = Cow::Borrowed(location.text(self.source));
+        self.tokens.push(Token::from_error(text, location));
+    }
+
+    pub fn push_token(&mut self, token: Token<'src>) {
         self.tokens.push(token);
     }
 
+    pub fn is_reserved(&self, keyword: &Keyword) -> bool {
+        self.extensions.reserves(keyword.dialect()) && keyword.standard() <= self.standard
+    }
+
+    /// Checks if a `<` starts a header name: after `#include` or `#embed` at the
+    /// start of a line, or after `__has_include(`.
+    pub fn expects_header_name(&self) -> bool {
+        let mut values = self
+            .tokens
+            .iter()
+            .rev()
+            .map(Token::get_value)
+            .filter(|value| !matches!(value, TokenValue::Comment(_)));
+        match (values.next(), values.next(), values.next()) {
+            (Some(TokenValue::Identifier(name)), Some(TokenValue::Symbol(Symbol::Hash)), None) => {
+                self.line_start && matches!(name.as_ref(), "include" | "embed")
+            }
+            (
+                Some(TokenValue::Symbol(Symbol::ParenthesisOpen)),
+                Some(TokenValue::Identifier(name)),
+                _,
+            ) => name == "__has_include",
+            _ => false,
+        }
+    }
+
+    pub const fn set_line_start(&mut self, line_start: bool) {
+        self.line_start = line_start;
+    }
+
+    pub const fn standard(&self) -> Standard {
+        self.standard
+    }
+
     pub const fn set_end_line(&mut self) {
         self.end_line = true;
     }
 
-    pub const fn newline(&mut self) {
-        self.failed = false;
+    pub fn newline(&mut self, removed: Vec<Removed>) {
         self.end_line = false;
+        self.removed = removed;
     }
 
     pub const fn is_end_line(&self) -> bool {
-        self.failed || self.end_line
+        self.end_line
     }
 }
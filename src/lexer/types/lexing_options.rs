@@ -0,0 +1,33 @@
+use super::keywords::Dialect;
+use super::source_encoding::SourceEncoding;
+use super::standard::Standard;
+
+#[derive(Debug, Default, Clone)]
+pub struct LexingOptions {
+    pub encoding: SourceEncoding,
+    pub extensions: Extensions,
+    /// Emits the comments as tokens instead of dropping them.
+    pub keep_comments: bool,
+    pub standard: Standard,
+    /// Replaces the trigraphs, with a warning for each of them.
+    pub trigraphs: bool,
+    /// Attaches the whitespace, comments and line splices to the tokens.
+    pub trivia: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Extensions {
+    pub gnu: bool,
+    pub msvc: bool,
+}
+
+impl Extensions {
+    pub const fn reserves(self, dialect: Dialect) -> bool {
+        match dialect {
+            Dialect::Gnu => self.gnu,
+            Dialect::GnuMsvc => self.gnu || self.msvc,
+            Dialect::Iso => true,
+            Dialect::Msvc => self.msvc,
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use super::tokens_types::Symbol;
+use super::standard::Standard;
+use super::tokens_types::{Encoding, StrValue, Symbol};
 use core::{mem, str::pattern::Pattern};
 
 const NULL: char = '\0';
@@ -30,11 +31,15 @@ impl Ident {
         self.first().unwrap_or('x').is_ascii_digit()
     }
 
+    fn is_hex(&self) -> bool {
+        self.0.starts_with("0x") || self.0.starts_with("0X")
+    }
+
     pub fn last_is_exp(&self) -> bool {
         self.is_number()
             && match self.0.chars().last() {
-                Some('p' | 'P') => self.0.starts_with("0x"),
-                Some('e' | 'E') => matches!(self.first().unwrap_or_default(), '1'..='9'),
+                Some('p' | 'P') => self.is_hex(),
+                Some('e' | 'E') => !self.is_hex(),
                 Some(_) | None => false,
             }
     }
@@ -47,6 +52,10 @@ impl Ident {
         self.0.push(ch);
     }
 
+    pub fn push_str(&mut self, text: &str) {
+        self.0.push_str(text);
+    }
+
     pub fn take_value(&mut self) -> String {
         mem::take(&mut self.0)
     }
@@ -63,8 +72,10 @@ pub enum LexingStatus {
     Unset,
     Symbols(SymbolStatus),
     Identifier(Ident),
-    Char(Option<char>),
-    Str(String),
+    /// Code units, and whether there was an invalid escape sequence.
+    Char(Encoding, Vec<u32>, bool),
+    /// Only built from the first escape sequence, the source being used before.
+    Str(Encoding, Option<StrValue<'static>>),
     Comment(CommentStatus),
 }
 
@@ -75,8 +86,8 @@ impl LexingStatus {
             Self::Unset => "no context",
             Self::Symbols(_) => "symbols",
             Self::Identifier(_) => "identifier",
-            Self::Char(_) => "char",
-            Self::Str(_) => "string",
+            Self::Char(..) => "char",
+            Self::Str(..) => "string",
             Self::Comment(_) => "comment",
         }
     }
@@ -96,8 +107,50 @@ impl LexingStatus {
         }
     }
 
-    pub fn new_ident(&mut self, ch: char) {
-        *self = Self::Identifier(Ident(String::from(ch)));
+    pub const fn encoding(&self) -> Encoding {
+        match self {
+            Self::Char(encoding, ..) | Self::Str(encoding, _) => *encoding,
+            Self::StartOfLine
+            | Self::Unset
+            | Self::Symbols(_)
+            | Self::Identifier(_)
+            | Self::Comment(_) => Encoding::Ordinary,
+        }
+    }
+
+    pub fn encoding_prefix(&self) -> Option<Encoding> {
+        if let Self::Identifier(ident) = self {
+            Encoding::from_prefix(ident.value())
+        } else {
+            None
+        }
+    }
+
+    pub fn new_ident(&mut self, ch: char, mut buffer: String) {
+        buffer.push(ch);
+        *self = Self::Identifier(Ident(buffer));
+    }
+
+    /// Checks if an identifier can start or go on here, outside of literals and
+    /// comments.
+    pub const fn accepts_identifier(&self) -> bool {
+        matches!(
+            self,
+            Self::Identifier(_) | Self::StartOfLine | Self::Symbols(_) | Self::Unset
+        )
+    }
+
+    /// Checks if the token being lexed ends before a `<`, whatever follows.
+    pub fn ends_before_lt(&self) -> bool {
+        match self {
+            Self::Identifier(_) | Self::StartOfLine | Self::Unset => true,
+            Self::Symbols(symbols) => symbols.last() == Some('('),
+            Self::Char(..) | Self::Str(..) | Self::Comment(_) => false,
+        }
+    }
+
+    pub fn new_ident_ucn(&mut self, spelling: &str) {
+        *self = Self::Identifier(Ident(spelling.to_owned()));
     }
 }
 
@@ -141,11 +194,23 @@ impl SymbolStatus {
         self.first == NULL && self.second == NULL && self.third == NULL
     }
 
-    pub fn push(&mut self, value: char) -> Option<(usize, Symbol)> {
+    pub fn len(&self) -> usize {
+        [self.first, self.second, self.third]
+            .into_iter()
+            .filter(|ch| *ch != NULL)
+            .count()
+    }
+
+    pub fn push(&mut self, value: char, standard: Standard) -> Option<(&'static str, Symbol)> {
+        if (self.first, self.second, self.third, value) == ('%', ':', '%', ':') {
+            // `%:%:` is the only punctuator longer than 3 characters
+            *self = Self::new(NULL);
+            return Some(("%:%:", Symbol::HashHash));
+        }
         let op = if self.third == NULL {
             None
         } else {
-            self.try_to_operator()
+            self.try_to_operator(standard)
         };
         if self.first == NULL {
             self.first = value;
@@ -159,10 +224,12 @@ impl SymbolStatus {
         op
     }
 
-    pub fn try_to_operator(&mut self) -> Option<(usize, Symbol)> {
+    /// Returns the first symbol, with its spelling. `::` is two `:` before C23.
+    pub fn try_to_operator(&mut self, standard: Standard) -> Option<(&'static str, Symbol)> {
         let result = match (self.first, self.second, self.third) {
             ('<', '<', '=') => Some((3, Symbol::LeftShiftAssign)),
             ('>', '>', '=') => Some((3, Symbol::RightShiftAssign)),
+            ('.', '.', '.') => Some((3, Symbol::Ellipsis)),
             ('-', '>', _) => Some((2, Symbol::Arrow)),
             ('+', '+', _) => Some((2, Symbol::Increment)),
             ('-', '-', _) => Some((2, Symbol::Decrement)),
@@ -182,6 +249,14 @@ impl SymbolStatus {
             ('&', '=', _) => Some((2, Symbol::AndAssign)),
             ('|', '=', _) => Some((2, Symbol::OrAssign)),
             ('^', '=', _) => Some((2, Symbol::XorAssign)),
+            ('#', '#', _) => Some((2, Symbol::HashHash)),
+            (':', ':', _) if standard >= Standard::C23 => Some((2, Symbol::ColonColon)),
+            // digraphs
+            ('<', ':', _) => Some((2, Symbol::BracketOpen)),
+            (':', '>', _) => Some((2, Symbol::BracketClose)),
+            ('<', '%', _) => Some((2, Symbol::BraceOpen)),
+            ('%', '>', _) => Some((2, Symbol::BraceClose)),
+            ('%', ':', _) => Some((2, Symbol::Hash)),
             ('+', _, _) => Some((1, Symbol::Plus)),
             ('-', _, _) => Some((1, Symbol::Minus)),
             ('(', _, _) => Some((1, Symbol::ParenthesisOpen)),
@@ -206,12 +281,25 @@ impl SymbolStatus {
             ('?', _, _) => Some((1, Symbol::Interrogation)),
             (':', _, _) => Some((1, Symbol::Colon)),
             (';', _, _) => Some((1, Symbol::SemiColon)),
+            ('#', _, _) => Some((1, Symbol::Hash)),
             (NULL, NULL, NULL) => None,
             _ => panic!(
                 "This is not meant to happen. Some unsupported symbols were found in the operator part of the lex_data. LexingData: {self:?}"
             ),
         };
 
+        let lexeme = result.as_ref().map(|(nb_consumed, symbol)| {
+            // only the digraphs are spelled differently from their symbol
+            match (self.first, self.second) {
+                _ if *nb_consumed == symbol.repr().len() => symbol.repr(),
+                ('<', ':') => "<:",
+                (':', '>') => ":>",
+                ('<', '%') => "<%",
+                ('%', '>') => "%>",
+                ('%', ':') => "%:",
+                _ => panic!("{symbol:?} isn't spelled with {nb_consumed} characters"),
+            }
+        });
         if let Some((nb_consumed, _)) = &result {
             match *nb_consumed {
             0 => (), // two consecutive litterals
@@ -233,7 +321,7 @@ impl SymbolStatus {
             _ => panic!("his is not meant to happen. nb_consumed is defined only be having values of 0, 1, 2 or 3, not {nb_consumed}"),
         };
         };
-        result
+        lexeme.zip(result.map(|(_, symbol)| symbol))
     }
 
     pub const fn new(ch: char) -> Self {
@@ -1,5 +1,11 @@
+pub mod char_class;
 pub mod escape_state;
 pub mod keywords;
 pub mod lexing_data;
+pub mod lexing_options;
 pub mod lexing_state;
+pub mod source_encoding;
+pub mod source_line;
+pub mod standard;
 pub mod tokens_types;
+pub mod trivia;
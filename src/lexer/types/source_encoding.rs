@@ -0,0 +1,28 @@
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SourceEncoding {
+    /// ISO-8859-1: each byte is the code point of the same value.
+    Latin1,
+    #[default]
+    Utf8,
+    /// Superset of Latin-1 with printable characters instead of the C1 controls.
+    Windows1252,
+}
+
+impl SourceEncoding {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "latin1" | "latin-1" | "iso-8859-1" | "iso8859-1" => Some(Self::Latin1),
+            "utf8" | "utf-8" => Some(Self::Utf8),
+            "windows-1252" | "cp1252" => Some(Self::Windows1252),
+            _ => None,
+        }
+    }
+
+    pub const fn repr(self) -> &'static str {
+        match self {
+            Self::Latin1 => "Latin-1",
+            Self::Utf8 => "UTF-8",
+            Self::Windows1252 => "Windows-1252",
+        }
+    }
+}
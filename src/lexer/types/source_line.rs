@@ -0,0 +1,116 @@
+use crate::errors::span::{FileId, Span};
+extern crate alloc;
+use alloc::borrow::Cow;
+
+/// Range of the source that doesn't appear as such in a [`SourceLine`].
+#[derive(Debug, Clone, Copy)]
+pub struct Removed {
+    pub start: usize,
+    pub end: usize,
+    /// End of a trigraph, that belongs to the character before it.
+    pub trigraph: bool,
+}
+
+/// Logical line being lexed: the physical lines joined by line splices, with
+/// their trigraphs replaced if they are enabled.
+pub struct SourceLine<'src> {
+    /// Indices in `text` after a trigraph or a line splice, with their offset in
+    /// the source and whether they follow a trigraph.
+    anchors: Vec<(usize, usize, bool)>,
+    file_id: FileId,
+    offset: usize,
+    text: Cow<'src, str>,
+    trigraphs: Vec<usize>,
+}
+
+impl<'src> SourceLine<'src> {
+    pub const fn new(text: &'src str, file_id: FileId, offset: usize) -> Self {
+        Self {
+            anchors: vec![],
+            file_id,
+            offset,
+            text: Cow::Borrowed(text),
+            trigraphs: vec![],
+        }
+    }
+
+    fn offset(&self, idx: usize) -> usize {
+        let before = self.anchors.partition_point(|(anchor, ..)| *anchor <= idx);
+        before
+            .checked_sub(1)
+            .and_then(|last| self.anchors.get(last))
+            .map_or(self.offset + idx, |(anchor, offset, _)| {
+                offset + idx - anchor
+            })
+    }
+
+    pub fn removed(&self) -> Vec<Removed> {
+        let mut previous = (0, self.offset);
+        self.anchors
+            .iter()
+            .map(|(anchor, offset, trigraph)| {
+                let start = previous.1 + anchor - previous.0;
+                previous = (*anchor, *offset);
+                Removed {
+                    start,
+                    end: *offset,
+                    trigraph: *trigraph,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the source span of the character of length `len` at `idx`.
+    pub fn span(&self, idx: usize, len: usize) -> Span {
+        let start = self.offset(idx);
+        if self.trigraphs.binary_search(&idx).is_ok() {
+            Span::new(self.file_id, start, start + 3)
+        } else {
+            Span::new(self.file_id, start, start + len)
+        }
+    }
+
+    /// Joins `next` to this line, in place of the splice starting at `backslash`.
+    pub fn splice(&mut self, next: Self, backslash: usize) {
+        let text = self.text.to_mut();
+        text.truncate(backslash);
+        text.push_str(&next.text);
+        self.anchors.retain(|(anchor, ..)| *anchor <= backslash);
+        self.anchors.push((backslash, next.offset, false));
+        self.anchors.extend(
+            next.anchors
+                .into_iter()
+                .map(|(anchor, offset, trigraph)| (anchor + backslash, offset, trigraph)),
+        );
+        self.trigraphs.retain(|trigraph| *trigraph < backslash);
+        self.trigraphs.extend(
+            next.trigraphs
+                .into_iter()
+                .map(|trigraph| trigraph + backslash),
+        );
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn with_trigraphs(
+        text: String,
+        file_id: FileId,
+        offset: usize,
+        trigraphs: Vec<usize>,
+    ) -> Self {
+        let anchors = trigraphs
+            .iter()
+            .zip(0..)
+            .map(|(trigraph, before)| (trigraph + 1, offset + trigraph + 2 * before + 3, true))
+            .collect();
+        Self {
+            anchors,
+            file_id,
+            offset,
+            text: Cow::Owned(text),
+            trigraphs,
+        }
+    }
+}
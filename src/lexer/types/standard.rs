@@ -0,0 +1,34 @@
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Standard {
+    /// ANSI C, also known as C90.
+    C89,
+    C99,
+    C11,
+    /// Also known as C18.
+    C17,
+    #[default]
+    C23,
+}
+
+impl Standard {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "c89" | "c90" | "ansi" => Some(Self::C89),
+            "c99" => Some(Self::C99),
+            "c11" => Some(Self::C11),
+            "c17" | "c18" => Some(Self::C17),
+            "c23" => Some(Self::C23),
+            _ => None,
+        }
+    }
+
+    pub const fn repr(self) -> &'static str {
+        match self {
+            Self::C89 => "C89",
+            Self::C99 => "C99",
+            Self::C11 => "C11",
+            Self::C17 => "C17",
+            Self::C23 => "C23",
+        }
+    }
+}
@@ -1,10 +1,257 @@
 use super::keywords::Keyword;
 use super::lexing_data::LexingData;
-use super::lexing_state::{Ident, LexingStatus};
-use crate::{errors::location::Location, lexer::numbers::Number};
-use core::fmt;
+use super::lexing_state::LexingStatus;
+use super::trivia::Trivia;
+use crate::{errors::span::Span, lexer::numbers::Number};
+use core::fmt::{self, Write as _};
+use core::mem;
+extern crate alloc;
+use alloc::borrow::Cow;
 
-#[derive(Debug, PartialEq, Eq)]
+/// Encoding prefix of a string literal or of a character constant.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Encoding {
+    #[default]
+    Ordinary,
+    Utf8,
+    Utf16,
+    Utf32,
+    Wide,
+}
+
+impl Encoding {
+    pub fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            "u8" => Some(Self::Utf8),
+            "u" => Some(Self::Utf16),
+            "U" => Some(Self::Utf32),
+            "L" => Some(Self::Wide),
+            _ => None,
+        }
+    }
+
+    pub const fn char_type(self) -> &'static str {
+        match self {
+            Self::Ordinary => "int",
+            Self::Utf8 => "unsigned char",
+            Self::Utf16 => "char16_t",
+            Self::Utf32 => "char32_t",
+            Self::Wide => "wchar_t",
+        }
+    }
+
+    /// Returns the C type of the elements of a literal with this encoding.
+    pub const fn element_type(self) -> &'static str {
+        match self {
+            Self::Ordinary => "char",
+            Self::Utf8 => "char8_t",
+            Self::Utf16 => "char16_t",
+            Self::Utf32 => "char32_t",
+            Self::Wide => "wchar_t",
+        }
+    }
+
+    /// Largest value of an octal or hexadecimal escape sequence in this encoding.
+    pub const fn max_escape_value(self) -> u32 {
+        match self {
+            Self::Ordinary | Self::Utf8 => 0xFF,
+            Self::Utf16 => 0xFFFF,
+            Self::Wide if cfg!(windows) => 0xFFFF,
+            Self::Utf32 | Self::Wide => u32::MAX,
+        }
+    }
+
+    pub fn code_units(self, ch: char) -> Vec<u32> {
+        match self {
+            Self::Ordinary | Self::Utf8 => {
+                let mut buffer = [0; 4];
+                ch.encode_utf8(&mut buffer).bytes().map(u32::from).collect()
+            }
+            Self::Utf16 => ch
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .map(|unit| u32::from(*unit))
+                .collect(),
+            Self::Wide if cfg!(windows) => ch
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .map(|unit| u32::from(*unit))
+                .collect(),
+            Self::Utf32 | Self::Wide => vec![u32::from(ch)],
+        }
+    }
+
+    pub fn decode(self, units: &[u32]) -> Option<String> {
+        match self {
+            Self::Ordinary | Self::Utf8 => {
+                let bytes = units
+                    .iter()
+                    .map(|unit| u8::try_from(*unit).ok())
+                    .collect::<Option<Vec<_>>>()?;
+                String::from_utf8(bytes).ok()
+            }
+            Self::Utf16 => Self::decode_utf16(units),
+            Self::Wide if cfg!(windows) => Self::decode_utf16(units),
+            Self::Utf32 | Self::Wide => units.iter().map(|unit| char::from_u32(*unit)).collect(),
+        }
+    }
+
+    fn decode_utf16(units: &[u32]) -> Option<String> {
+        let units_16 = units
+            .iter()
+            .map(|unit| u16::try_from(*unit).ok())
+            .collect::<Option<Vec<_>>>()?;
+        char::decode_utf16(units_16).collect::<Result<_, _>>().ok()
+    }
+
+    pub const fn prefix(self) -> &'static str {
+        match self {
+            Self::Ordinary => "",
+            Self::Utf8 => "u8",
+            Self::Utf16 => "u",
+            Self::Utf32 => "U",
+            Self::Wide => "L",
+        }
+    }
+}
+
+/// Whether `char` is signed on the target: it is unsigned on ARM, `PowerPC`,
+/// RISC-V and s390x, except on Apple and Windows targets.
+const CHAR_IS_SIGNED: bool = cfg!(any(target_vendor = "apple", windows))
+    || !cfg!(any(
+        target_arch = "aarch64",
+        target_arch = "arm",
+        target_arch = "powerpc",
+        target_arch = "powerpc64",
+        target_arch = "riscv32",
+        target_arch = "riscv64",
+        target_arch = "s390x"
+    ));
+
+/// Character constant, like `'a'`, `'ab'` or `L'x'`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CharConstant {
+    encoding: Encoding,
+    units: Vec<u32>,
+    /// Value of the constant, converted to its type.
+    value: i64,
+}
+
+impl CharConstant {
+    /// Builds a constant from its non-empty code units, with the value GCC gives
+    /// to multi-character constants.
+    pub fn new(encoding: Encoding, units: Vec<u32>) -> Self {
+        let first = units.first().copied().unwrap_or_default();
+        let value = match encoding {
+            Encoding::Ordinary if units.len() == 1 && CHAR_IS_SIGNED => {
+                let byte = u8::try_from(first & 0xFF).unwrap_or_default();
+                i64::from(byte.cast_signed())
+            }
+            Encoding::Ordinary => {
+                let int = units
+                    .iter()
+                    .fold(0u32, |acc, unit| acc.wrapping_shl(8) | (unit & 0xFF));
+                i64::from(int.cast_signed())
+            }
+            Encoding::Wide => {
+                let last = units.last().copied().unwrap_or_default();
+                if cfg!(windows) {
+                    i64::from(last)
+                } else {
+                    i64::from(last.cast_signed())
+                }
+            }
+            Encoding::Utf8 | Encoding::Utf16 | Encoding::Utf32 => i64::from(first),
+        };
+        Self {
+            encoding,
+            units,
+            value,
+        }
+    }
+
+    pub const fn c_type(&self) -> &'static str {
+        self.encoding.char_type()
+    }
+
+    pub const fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn units(&self) -> &[u32] {
+        &self.units
+    }
+
+    pub const fn value(&self) -> i64 {
+        self.value
+    }
+}
+
+/// Value of a string literal, kept as code units when they aren't valid text
+/// (`u"\xD800"`).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum StrValue<'src> {
+    Text(Cow<'src, str>),
+    Units(Vec<u32>),
+}
+
+impl Default for StrValue<'_> {
+    fn default() -> Self {
+        Self::Text(Cow::Borrowed(""))
+    }
+}
+
+impl StrValue<'_> {
+    pub fn from_units(encoding: Encoding, units: Vec<u32>) -> StrValue<'static> {
+        encoding.decode(&units).map_or_else(
+            || StrValue::Units(units),
+            |text| StrValue::Text(Cow::Owned(text)),
+        )
+    }
+
+    pub fn into_units(self, encoding: Encoding) -> Vec<u32> {
+        match self {
+            Self::Text(text) => text
+                .chars()
+                .flat_map(|ch| encoding.code_units(ch))
+                .collect(),
+            Self::Units(units) => units,
+        }
+    }
+
+    /// Returns the value as text, with U+FFFD for the invalid code units.
+    pub fn into_lossy_text(self) -> String {
+        match self {
+            Self::Text(text) => text.into_owned(),
+            Self::Units(units) => units
+                .into_iter()
+                .map(|unit| char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect(),
+        }
+    }
+
+    pub fn into_owned(self) -> StrValue<'static> {
+        match self {
+            Self::Text(text) => StrValue::Text(Cow::Owned(text.into_owned())),
+            Self::Units(units) => StrValue::Units(units),
+        }
+    }
+
+    pub fn push_unit(&mut self, unit: u32, encoding: Encoding) {
+        let mut units = mem::take(self).into_units(encoding);
+        units.push(unit);
+        *self = Self::Units(units);
+    }
+
+    pub fn push_str(&mut self, text: &str, encoding: Encoding) {
+        match self {
+            Self::Text(value) => value.to_mut().push_str(text),
+            Self::Units(units) => units.extend(text.chars().flat_map(|ch| encoding.code_units(ch))),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Symbol {
     // one character
     Ampercent,
@@ -21,6 +268,7 @@ pub enum Symbol {
     Divide,
     Dot,
     Gt,
+    Hash,
     Interrogation,
     LogicalNot,
     Lt,
@@ -35,11 +283,13 @@ pub enum Symbol {
     AddAssign,
     AndAssign,
     Arrow,
+    ColonColon,
     Decrement,
     Different,
     DivAssign,
     Equal,
     Ge,
+    HashHash,
     Increment,
     Le,
     LogicalAnd,
@@ -52,86 +302,368 @@ pub enum Symbol {
     SubAssign,
     XorAssign,
     // three characters
+    Ellipsis,
     LeftShiftAssign,
     RightShiftAssign,
 }
-pub struct Token {
-    location: Location,
-    value: TokenValue,
-}
 
-impl Token {
-    pub fn from_char(ch: char, location: &Location) -> Self {
-        Self {
-            value: TokenValue::Char(ch),
-            location: location.to_owned().into_past(1),
+impl Symbol {
+    pub const fn repr(&self) -> &'static str {
+        match self {
+            Self::Ampercent => "&",
+            Self::Assign => "=",
+            Self::BitwiseNot => "~",
+            Self::BitwiseOr => "|",
+            Self::BitwiseXor => "^",
+            Self::BraceClose => "}",
+            Self::BraceOpen => "{",
+            Self::BracketClose => "]",
+            Self::BracketOpen => "[",
+            Self::Colon => ":",
+            Self::Comma => ",",
+            Self::Divide => "/",
+            Self::Dot => ".",
+            Self::Gt => ">",
+            Self::Hash => "#",
+            Self::Interrogation => "?",
+            Self::LogicalNot => "!",
+            Self::Lt => "<",
+            Self::Minus => "-",
+            Self::Modulo => "%",
+            Self::ParenthesisClose => ")",
+            Self::ParenthesisOpen => "(",
+            Self::Plus => "+",
+            Self::SemiColon => ";",
+            Self::Star => "*",
+            Self::AddAssign => "+=",
+            Self::AndAssign => "&=",
+            Self::Arrow => "->",
+            Self::ColonColon => "::",
+            Self::Decrement => "--",
+            Self::Different => "!=",
+            Self::DivAssign => "/=",
+            Self::Equal => "==",
+            Self::Ge => ">=",
+            Self::HashHash => "##",
+            Self::Increment => "++",
+            Self::Le => "<=",
+            Self::LogicalAnd => "&&",
+            Self::LogicalOr => "||",
+            Self::ModAssign => "%=",
+            Self::MulAssign => "*=",
+            Self::OrAssign => "|=",
+            Self::LeftShift => "<<",
+            Self::RightShift => ">>",
+            Self::SubAssign => "-=",
+            Self::XorAssign => "^=",
+            Self::Ellipsis => "...",
+            Self::LeftShiftAssign => "<<=",
+            Self::RightShiftAssign => ">>=",
         }
     }
+}
+
+/// Token of the source, that borrows its text from the source when it can.
+pub struct Token<'src> {
+    /// Only comments are between the last new-line and this token.
+    first_on_line: bool,
+    /// Trivia since the first line terminator after the previous token.
+    leading_trivia: Vec<Trivia>,
+    /// Spelling of the token in the source.
+    lexeme: Cow<'src, str>,
+    location: Span,
+    /// Trivia after this token, up to the next line terminator.
+    trailing_trivia: Vec<Trivia>,
+    value: TokenValue<'src>,
+}
+
+impl<'src> Token<'src> {
+    pub const fn from_char(constant: CharConstant, lexeme: Cow<'src, str>, location: Span) -> Self {
+        Self::from_value_lexeme(TokenValue::Char(constant), lexeme, location)
+    }
 
-    pub fn from_identifier(identifier: &mut Ident, location: &Location) -> Self {
-        let value = identifier.take_value();
-        let token_value = Keyword::try_from(value.as_str())
-            .map_err(|()| value)
-            .map_or_else(TokenValue::Identifier, TokenValue::Keyword);
-        Self {
-            location: location.to_owned().into_past(identifier.len()),
-            value: token_value,
-        }
+    pub fn from_error(text: Cow<'src, str>, location: Span) -> Self {
+        Self::from_value_lexeme(TokenValue::Error(text.clone()), text, location)
     }
 
-    pub fn from_number(number: Number, location: &Location) -> Self {
-        Self {
-            value: TokenValue::Number(number),
-            location: location.to_owned(),
-        }
+    pub const fn from_header_name(
+        name: Cow<'src, str>,
+        lexeme: Cow<'src, str>,
+        location: Span,
+    ) -> Self {
+        Self::from_value_lexeme(TokenValue::HeaderName(name), lexeme, location)
     }
 
-    pub fn from_str(str: String, location: &Location) -> Self {
-        Self {
-            location: location.to_owned().into_past(str.len()),
-            value: TokenValue::Str(str),
-        }
+    /// Builds an identifier, or a keyword if it is reserved with the options of
+    /// `lex_data`.
+    pub fn from_identifier(
+        name: Cow<'src, str>,
+        lexeme: Cow<'src, str>,
+        location: Span,
+        lex_data: &LexingData,
+    ) -> Self {
+        let token_value = Keyword::try_from(name.as_ref())
+            .ok()
+            .filter(|keyword| lex_data.is_reserved(keyword))
+            .map_or(TokenValue::Identifier(name), TokenValue::Keyword);
+        Self::from_value_lexeme(token_value, lexeme, location)
+    }
+
+    pub const fn from_number(number: Number, lexeme: Cow<'src, str>, location: Span) -> Self {
+        Self::from_value_lexeme(TokenValue::Number(number), lexeme, location)
+    }
+
+    pub const fn from_str(
+        encoding: Encoding,
+        str: StrValue<'src>,
+        lexeme: Cow<'src, str>,
+        location: Span,
+    ) -> Self {
+        Self::from_value_lexeme(TokenValue::Str(encoding, str), lexeme, location)
+    }
+
+    pub const fn from_symbol(symbol: Symbol, lexeme: Cow<'src, str>, location: Span) -> Self {
+        Self::from_value_lexeme(TokenValue::Symbol(symbol), lexeme, location)
+    }
+
+    /// Builds a token that isn't in the source, spelled as its value is displayed.
+    pub fn from_value(value: TokenValue<'src>, location: Span) -> Self {
+        let lexeme = Cow::Owned(value.to_string());
+        Self::from_value_lexeme(value, lexeme, location)
     }
 
-    pub fn from_symbol(symbol: Symbol, size: usize, location: &Location) -> Self {
+    const fn from_value_lexeme(
+        value: TokenValue<'src>,
+        lexeme: Cow<'src, str>,
+        location: Span,
+    ) -> Self {
         Self {
-            value: TokenValue::Symbol(symbol),
-            location: location.to_owned().into_past(size),
+            first_on_line: false,
+            leading_trivia: vec![],
+            lexeme,
+            location,
+            trailing_trivia: vec![],
+            value,
         }
     }
 
-    pub fn into_value(self) -> TokenValue {
+    pub fn into_value(self) -> TokenValue<'src> {
         self.value
     }
 
-    pub fn into_value_location(self) -> (TokenValue, Location) {
+    pub fn into_value_location(self) -> (TokenValue<'src>, Span) {
         (self.value, self.location)
     }
 
-    pub const fn get_value(&self) -> &TokenValue {
+    pub fn into_owned(self) -> Token<'static> {
+        Token {
+            first_on_line: self.first_on_line,
+            leading_trivia: self.leading_trivia,
+            lexeme: Cow::Owned(self.lexeme.into_owned()),
+            location: self.location,
+            trailing_trivia: self.trailing_trivia,
+            value: self.value.into_owned(),
+        }
+    }
+
+    pub const fn is_first_on_line(&self) -> bool {
+        self.first_on_line
+    }
+
+    pub const fn set_first_on_line(&mut self) {
+        self.first_on_line = true;
+    }
+
+    pub const fn get_value(&self) -> &TokenValue<'src> {
         &self.value
     }
+
+    pub const fn get_location(&self) -> &Span {
+        &self.location
+    }
+
+    pub const fn location_mut(&mut self) -> &mut Span {
+        &mut self.location
+    }
+
+    pub fn get_leading_trivia(&self) -> &[Trivia] {
+        &self.leading_trivia
+    }
+
+    pub const fn leading_trivia_mut(&mut self) -> &mut Vec<Trivia> {
+        &mut self.leading_trivia
+    }
+
+    pub fn get_trailing_trivia(&self) -> &[Trivia] {
+        &self.trailing_trivia
+    }
+
+    pub const fn trailing_trivia_mut(&mut self) -> &mut Vec<Trivia> {
+        &mut self.trailing_trivia
+    }
+
+    pub fn get_lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    pub const fn lexeme_mut(&mut self) -> &mut Cow<'src, str> {
+        &mut self.lexeme
+    }
+
+    pub const fn value_mut(&mut self) -> &mut TokenValue<'src> {
+        &mut self.value
+    }
 }
 
 #[expect(clippy::min_ident_chars)]
-impl fmt::Debug for Token {
+impl fmt::Debug for Token<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.value.fmt(f)
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub enum TokenValue {
-    Char(char),
-    Identifier(String),
+#[derive(Debug, PartialEq, Clone)]
+pub enum TokenValue<'src> {
+    Char(CharConstant),
+    Comment(String),
+    /// Source text that failed to lex, whose error was already reported.
+    Error(Cow<'src, str>),
+    /// File name of a `<file>` header name.
+    HeaderName(Cow<'src, str>),
+    Identifier(Cow<'src, str>),
     Keyword(Keyword),
     Number(Number),
-    Str(String),
+    /// Content of a `#pragma` directive or of a `_Pragma` operator.
+    Pragma(String),
+    Str(Encoding, StrValue<'src>),
     Symbol(Symbol),
 }
 
+impl TokenValue<'_> {
+    pub fn into_owned(self) -> TokenValue<'static> {
+        match self {
+            Self::Char(constant) => TokenValue::Char(constant),
+            Self::Comment(comment) => TokenValue::Comment(comment),
+            Self::Error(text) => TokenValue::Error(Cow::Owned(text.into_owned())),
+            Self::HeaderName(name) => TokenValue::HeaderName(Cow::Owned(name.into_owned())),
+            Self::Identifier(identifier) => {
+                TokenValue::Identifier(Cow::Owned(identifier.into_owned()))
+            }
+            Self::Keyword(keyword) => TokenValue::Keyword(keyword),
+            Self::Number(number) => TokenValue::Number(number),
+            Self::Pragma(pragma) => TokenValue::Pragma(pragma),
+            Self::Str(encoding, val) => TokenValue::Str(encoding, val.into_owned()),
+            Self::Symbol(symbol) => TokenValue::Symbol(symbol),
+        }
+    }
+}
+
+fn push_escaped(dest: &mut String, ch: char, quote: char) {
+    match ch {
+        '\\' => dest.push_str("\\\\"),
+        '\n' => dest.push_str("\\n"),
+        '\t' => dest.push_str("\\t"),
+        '\r' => dest.push_str("\\r"),
+        _ if ch == quote => {
+            dest.push('\\');
+            dest.push(ch);
+        }
+        _ if ch.is_ascii_control() => {
+            write!(dest, "\\{:03o}", u32::from(ch)).expect("writing to a String never fails");
+        }
+        _ => dest.push(ch),
+    }
+}
+
+fn escape_str(val: &str) -> String {
+    let mut escaped = String::from('"');
+    for ch in val.chars() {
+        push_escaped(&mut escaped, ch, '"');
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn escape_units(encoding: Encoding, units: &[u32]) -> String {
+    let mut escaped = String::from('"');
+    let mut after_hexadecimal = false;
+    for unit in units {
+        match char::from_u32(*unit) {
+            // a digit after `\x...` would be part of the sequence
+            Some(ch) if ch.is_ascii() && !(after_hexadecimal && ch.is_ascii_hexdigit()) => {
+                push_escaped(&mut escaped, ch, '"');
+                after_hexadecimal = false;
+            }
+            Some(_) | None if *unit <= 0o777 => {
+                write!(escaped, "\\{unit:03o}").expect("writing to a String never fails");
+                after_hexadecimal = false;
+            }
+            Some(ch) if !matches!(encoding, Encoding::Ordinary | Encoding::Utf8) => {
+                escaped.push(ch);
+                after_hexadecimal = false;
+            }
+            Some(_) | None => {
+                write!(escaped, "\\x{unit:x}").expect("writing to a String never fails");
+                after_hexadecimal = true;
+            }
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn escape_char_constant(constant: &CharConstant) -> String {
+    let mut escaped = String::from('\'');
+    for unit in constant.units() {
+        match char::from_u32(*unit) {
+            Some(ch)
+                if ch.is_ascii()
+                    || !matches!(constant.encoding(), Encoding::Ordinary | Encoding::Utf8) =>
+            {
+                push_escaped(&mut escaped, ch, '\'');
+            }
+            Some(_) | None if *unit <= 0o777 => {
+                write!(escaped, "\\{unit:03o}").expect("writing to a String never fails");
+            }
+            Some(_) | None => {
+                write!(escaped, "\\x{unit:x}").expect("writing to a String never fails");
+            }
+        }
+    }
+    escaped.push('\'');
+    escaped
+}
+
+#[allow(clippy::min_ident_chars)]
+impl fmt::Display for TokenValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Char(constant) => write!(
+                f,
+                "{}{}",
+                constant.encoding().prefix(),
+                escape_char_constant(constant)
+            ),
+            Self::Comment(comment) => f.write_str(comment),
+            Self::Error(text) => f.write_str(text),
+            Self::HeaderName(name) => write!(f, "<{name}>"),
+            Self::Identifier(identifier) => f.write_str(identifier),
+            Self::Keyword(keyword) => f.write_str(keyword.repr()),
+            Self::Number(number) => number.fmt(f),
+            Self::Pragma(pragma) => write!(f, "_Pragma({})", escape_str(pragma)),
+            Self::Str(encoding, StrValue::Text(val)) => {
+                write!(f, "{}{}", encoding.prefix(), escape_str(val))
+            }
+            Self::Str(encoding, StrValue::Units(units)) => {
+                write!(f, "{}{}", encoding.prefix(), escape_units(*encoding, units))
+            }
+            Self::Symbol(symbol) => f.write_str(symbol.repr()),
+        }
+    }
+}
+
 pub struct LexingStruct<'lex_char> {
-    data: &'lex_char mut LexingData,
+    data: &'lex_char mut LexingData<'lex_char>,
     status: &'lex_char mut LexingStatus,
-    location: &'lex_char Location,
+    location: &'lex_char Span,
 }
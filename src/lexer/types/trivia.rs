@@ -0,0 +1,32 @@
+use crate::errors::span::Span;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TriviaKind {
+    BlockComment,
+    /// Without the line terminator.
+    LineComment,
+    LineSplice,
+    Whitespace,
+}
+
+/// Piece of source that isn't part of any token, whose text is the text of its
+/// span.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Trivia {
+    kind: TriviaKind,
+    location: Span,
+}
+
+impl Trivia {
+    pub const fn new(kind: TriviaKind, location: Span) -> Self {
+        Self { kind, location }
+    }
+
+    pub const fn get_kind(&self) -> TriviaKind {
+        self.kind
+    }
+
+    pub const fn get_location(&self) -> &Span {
+        &self.location
+    }
+}
@@ -36,34 +36,168 @@
 )]
 //
 #![feature(is_ascii_octdigit, f128, concat_idents, pattern)]
+#![cfg_attr(test, feature(test))]
 
 mod errors;
 mod lexer;
 mod parser;
+mod preprocessor;
 #[cfg(test)]
 mod test;
-use errors::{compile::Res, display::display_errors, location::Location};
-use lexer::lex_file;
+use errors::{compile::Res, display::display_errors, source_map::SourceMap};
+use lexer::{
+    api::{lexing_options::LexingOptions, source_encoding::SourceEncoding, standard::Standard},
+    concat::concat_strings,
+    decode::decode_file,
+    lex_file,
+};
+use parser::docs::extract_docs;
+use preprocessor::{
+    config::PreprocessorConfig,
+    dependencies::{default_target, make_rule},
+    output::preprocessed_output,
+    preprocess,
+    state::PreprocessorState,
+};
+use std::path::{Path, PathBuf};
 use std::{env, fs};
 
 const DIR: &str = "./data/";
 
-#[expect(clippy::panic, clippy::dbg_macro)]
+enum DependencyOutput {
+    /// `-M`: print the dependencies instead of compiling.
+    Stdout,
+    /// `-MD`: write the dependencies to a `.d` file.
+    File,
+}
+
+#[derive(Default)]
+struct Args {
+    dependencies: Option<DependencyOutput>,
+    filename: Option<String>,
+    lexing: LexingOptions,
+    /// `-MP`: add a phony target for each dependency.
+    phony_targets: bool,
+    /// `-E`: print the preprocessed source.
+    preprocess_only: bool,
+    /// `-MT`: target of the dependency rule.
+    target: Option<String>,
+}
+
+impl Args {
+    fn parse<I>(mut env_args: I) -> Self
+    where
+        I: Iterator<Item = String>,
+    {
+        let mut args = Self::default();
+        while let Some(arg) = env_args.next() {
+            match arg.as_str() {
+                "-C" => args.lexing.keep_comments = true,
+                "-E" => args.preprocess_only = true,
+                "-M" => args.dependencies = Some(DependencyOutput::Stdout),
+                "-MD" => args.dependencies = Some(DependencyOutput::File),
+                "-MP" => args.phony_targets = true,
+                "-MT" => match env_args.next() {
+                    Some(target) => args.target = Some(target),
+                    None => eprintln!("Missing target after '-MT': using the default one."),
+                },
+                "-trigraphs" => args.lexing.trigraphs = true,
+                "-fms-extensions" => args.lexing.extensions.msvc = true,
+                _ if arg.starts_with("-finput-charset=") => {
+                    let name = arg.trim_start_matches("-finput-charset=");
+                    match SourceEncoding::from_name(name) {
+                        Some(encoding) => args.lexing.encoding = encoding,
+                        None => eprintln!("Unknown encoding '{name}': using UTF-8."),
+                    }
+                }
+                _ if arg.starts_with("-std=") => {
+                    let name = arg.trim_start_matches("-std=");
+                    // `gnuXX` is the standard `cXX` with the GNU extensions
+                    let gnu = name.strip_prefix("gnu");
+                    let standard = gnu.map_or_else(
+                        || Standard::from_name(name),
+                        |version| Standard::from_name(&format!("c{version}")),
+                    );
+                    match standard {
+                        Some(selected) => {
+                            args.lexing.standard = selected;
+                            args.lexing.extensions.gnu = gnu.is_some();
+                        }
+                        None => eprintln!("Unknown standard '{name}': using the default one."),
+                    }
+                }
+                _ => args.filename = Some(arg),
+            }
+        }
+        args
+    }
+}
+
+#[expect(clippy::panic, clippy::dbg_macro, clippy::print_stdout)]
 fn main() {
-    let filename = env::args().nth(1).unwrap_or_else(|| "test".to_owned());
+    let args = Args::parse(env::args().skip(1));
+    let filename = args.filename.as_deref().unwrap_or("test");
     let path = format!("{DIR}{filename}.c");
-    let content: &str = &fs::read_to_string(&path).unwrap_or_else(|_| {
+    let bytes = fs::read(&path).unwrap_or_else(|_| {
         panic!(
             "The provided path is incorrect. No file found at {}.",
             &path
         )
     });
-    let files: &[(String, &str)] = &[(path.clone(), content)];
-    let mut location = Location::from(path);
+    let mut source_map = SourceMap::default();
+    let Res {
+        result: file_id,
+        mut errors,
+    } = decode_file(&mut source_map, path.clone(), &bytes, &args.lexing);
+    let source = source_map.source(file_id);
+    let Res {
+        result: lexed,
+        errors: lexer_errors,
+    } = lex_file(&source, file_id, &args.lexing);
+    errors.extend(lexer_errors);
+    let mut config = PreprocessorConfig::default();
+    config.set_lexing_options(args.lexing.clone());
+    config.define_target_macros();
+    let mut state = PreprocessorState::new();
+    state.add_dependency(PathBuf::from(&path));
     let Res {
         result: tokens,
-        errors,
-    } = lex_file(content, &mut location);
-    dbg!(&tokens);
-    display_errors(errors, files);
+        errors: preprocessor_errors,
+    } = preprocess(lexed, &mut source_map, &config, &mut state);
+    errors.extend(preprocessor_errors);
+    if let Some(output) = &args.dependencies {
+        let target = args.target.unwrap_or_else(|| default_target(&path));
+        let rule = make_rule(&target, state.dependencies(), args.phony_targets);
+        match output {
+            DependencyOutput::Stdout => {
+                print!("{rule}");
+                display_errors(errors, &source_map);
+                return;
+            }
+            DependencyOutput::File => {
+                let dependency_file = Path::new(&default_target(&path)).with_extension("d");
+                fs::write(&dependency_file, rule).unwrap_or_else(|err| {
+                    panic!(
+                        "Failed to write dependency file {}: {err}",
+                        dependency_file.display()
+                    )
+                });
+            }
+        }
+    }
+    if args.preprocess_only {
+        print!("{}", preprocessed_output(&tokens, &source_map));
+    } else {
+        let Res {
+            result: concatenated,
+            errors: concat_errors,
+        } = concat_strings(tokens, &source_map);
+        errors.extend(concat_errors);
+        dbg!(&concatenated);
+        if args.lexing.keep_comments {
+            // the documentation comments are only kept with `-C`
+            print!("{}", extract_docs(&concatenated, &source_map));
+        }
+    }
+    display_errors(errors, &source_map);
 }
@@ -0,0 +1,422 @@
+use crate::errors::{source_map::SourceMap, span::Span};
+use crate::lexer::api::keywords::Keyword;
+use crate::lexer::api::tokens_types::{Symbol, Token, TokenValue};
+extern crate alloc;
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum DeclarationKind {
+    Enum,
+    Function,
+    Struct,
+    Typedef,
+    Union,
+    Variable,
+}
+
+#[allow(clippy::min_ident_chars)]
+impl fmt::Display for DeclarationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Enum => "enum",
+                Self::Function => "function",
+                Self::Struct => "struct",
+                Self::Typedef => "typedef",
+                Self::Union => "union",
+                Self::Variable => "variable",
+            }
+        )
+    }
+}
+
+/// Part of a documentation comment that the text being read belongs to.
+#[derive(Debug, Clone, Copy)]
+enum Section {
+    Brief,
+    Details,
+    Param(usize),
+    Returns,
+}
+
+/// Documentation of a declaration, parsed from its Doxygen comments.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Documentation {
+    /// `@brief` paragraph, or first paragraph if there is no `@brief` tag.
+    brief: String,
+    /// Other paragraphs, with the unknown tags.
+    details: String,
+    params: Vec<(String, String)>,
+    returns: Option<String>,
+}
+
+impl Documentation {
+    pub fn parse(text: &str) -> Self {
+        let mut doc = Self::default();
+        let mut section = Section::Brief;
+        let mut paragraph_end = false;
+        for raw_line in text.lines() {
+            let line = raw_line.trim().trim_start_matches('*').trim();
+            if line.is_empty() {
+                // the blank lines before the first paragraph don't end it
+                if doc.brief.is_empty() && matches!(section, Section::Brief) {
+                    continue;
+                }
+                paragraph_end = true;
+                section = Section::Details;
+                continue;
+            }
+            let (tag, rest) = split_tag(line);
+            let content = match tag {
+                Some("brief" | "short") => {
+                    section = Section::Brief;
+                    rest
+                }
+                Some(param) if param == "param" || param.starts_with("param[") => {
+                    let (name, description) = split_word(rest);
+                    doc.params.push((name.to_owned(), String::new()));
+                    section = Section::Param(doc.params.len() - 1);
+                    description
+                }
+                Some("return" | "returns" | "result") => {
+                    section = Section::Returns;
+                    rest
+                }
+                Some(_) => {
+                    section = Section::Details;
+                    line
+                }
+                None => line,
+            };
+            let dest = match section {
+                Section::Brief => &mut doc.brief,
+                Section::Details => {
+                    if paragraph_end && !doc.details.is_empty() {
+                        doc.details.push_str("\n\n");
+                    }
+                    &mut doc.details
+                }
+                Section::Param(idx) => match doc.params.get_mut(idx) {
+                    Some((_, description)) => description,
+                    None => continue,
+                },
+                Section::Returns => doc.returns.get_or_insert_with(String::new),
+            };
+            paragraph_end = false;
+            if !content.is_empty() {
+                if !dest.is_empty() && !dest.ends_with('\n') {
+                    dest.push(' ');
+                }
+                dest.push_str(content);
+            }
+        }
+        doc
+    }
+
+    pub fn get_brief(&self) -> &str {
+        &self.brief
+    }
+
+    pub fn get_details(&self) -> &str {
+        &self.details
+    }
+
+    pub fn get_param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, description)| description.as_str())
+    }
+
+    pub fn get_params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    pub fn get_returns(&self) -> Option<&str> {
+        self.returns.as_deref()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DocIndex {
+    docs: BTreeMap<(DeclarationKind, String), Documentation>,
+}
+
+impl DocIndex {
+    pub fn get(&self, kind: DeclarationKind, name: &str) -> Option<&Documentation> {
+        self.docs.get(&(kind, name.to_owned()))
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<&Documentation> {
+        self.get(DeclarationKind::Function, name)
+    }
+
+    pub fn get_struct(&self, name: &str) -> Option<&Documentation> {
+        self.get(DeclarationKind::Struct, name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (DeclarationKind, &str, &Documentation)> {
+        self.docs
+            .iter()
+            .map(|((kind, name), doc)| (*kind, name.as_str(), doc))
+    }
+}
+
+/// One line per declaration, then one indented line per part of its
+/// documentation.
+#[allow(clippy::min_ident_chars)]
+impl fmt::Display for DocIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (kind, name, doc) in self.iter() {
+            writeln!(f, "{kind} {name}")?;
+            if !doc.brief.is_empty() {
+                writeln!(f, "    brief: {}", doc.brief)?;
+            }
+            for (param, description) in &doc.params {
+                writeln!(f, "    param {param}: {description}")?;
+            }
+            if let Some(returns) = &doc.returns {
+                writeln!(f, "    returns: {returns}")?;
+            }
+            for paragraph in doc.details.split("\n\n").filter(|text| !text.is_empty()) {
+                writeln!(f, "    {paragraph}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn split_tag(line: &str) -> (Option<&str>, &str) {
+    line.strip_prefix(['@', '\\'])
+        .filter(|after| after.starts_with(|ch: char| ch.is_ascii_alphabetic()))
+        .map_or((None, line), |after| {
+            let (tag, rest) = split_word(after);
+            (Some(tag), rest)
+        })
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let trimmed = text.trim_start();
+    trimmed
+        .split_once(char::is_whitespace)
+        .map_or((trimmed, ""), |(word, rest)| (word, rest.trim_start()))
+}
+
+/// Returns the text of a Doxygen comment, and whether it documents the
+/// previous declaration (`///<`, `//!<`).
+fn doc_text(comment: &str) -> Option<(&str, bool)> {
+    let body = if let Some(block) = comment
+        .strip_prefix("/**")
+        .or_else(|| comment.strip_prefix("/*!"))
+    {
+        block.strip_suffix("*/")?
+    } else {
+        comment
+            .strip_prefix("///")
+            .or_else(|| comment.strip_prefix("//!"))
+            .filter(|line| !line.starts_with('/'))?
+    };
+    Some(
+        body.strip_prefix('<')
+            .map_or((body, false), |trailing| (trailing, true)),
+    )
+}
+
+/// Checks if a blank line, or a file change, separates `before` from `after`.
+fn detached(before: &Span, after: &Span, source_map: &SourceMap) -> bool {
+    before.file_id() != after.file_id()
+        || source_map
+            .content(before.file_id())
+            .get(before.end()..after.start())
+            .is_some_and(|between| between.matches('\n').count() >= 2)
+}
+
+/// Returns the index after the brace closing the one opened before `start`.
+fn skip_braces(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 1usize;
+    for (idx, token) in tokens.iter().enumerate().skip(start) {
+        match token.get_value() {
+            TokenValue::Symbol(Symbol::BraceOpen) => depth += 1,
+            TokenValue::Symbol(Symbol::BraceClose) => {
+                depth -= 1;
+                if depth == 0 {
+                    return idx + 1;
+                }
+            }
+            TokenValue::Char(_)
+            | TokenValue::Comment(_)
+            | TokenValue::Error(_)
+            | TokenValue::HeaderName(_)
+            | TokenValue::Identifier(_)
+            | TokenValue::Keyword(_)
+            | TokenValue::Number(_)
+            | TokenValue::Pragma(_)
+            | TokenValue::Str(..)
+            | TokenValue::Symbol(_) => (),
+        }
+    }
+    tokens.len()
+}
+
+/// Returns the index after the declaration at `start`, and the names it
+/// declares.
+fn read_declaration(tokens: &[Token], start: usize) -> (usize, Vec<(DeclarationKind, String)>) {
+    let mut typedef = false;
+    // tag of a struct, union or enum, and whether the declaration declares it
+    let mut tag: Option<(DeclarationKind, String, bool)> = None;
+    let mut name: Option<String> = None;
+    let mut function = false;
+    let mut depth = 0usize;
+    let mut idx = start;
+    while let Some(token) = tokens.get(idx) {
+        idx += 1;
+        match token.get_value() {
+            TokenValue::Keyword(Keyword::Typedef) if depth == 0 => typedef = true,
+            TokenValue::Keyword(keyword @ (Keyword::Struct | Keyword::Union | Keyword::Enum))
+                if depth == 0 && tag.is_none() =>
+            {
+                let kind = if *keyword == Keyword::Struct {
+                    DeclarationKind::Struct
+                } else if *keyword == Keyword::Union {
+                    DeclarationKind::Union
+                } else {
+                    DeclarationKind::Enum
+                };
+                if let Some(TokenValue::Identifier(ident)) = tokens.get(idx).map(Token::get_value) {
+                    idx += 1;
+                    let declared = matches!(
+                        tokens.get(idx).map(Token::get_value),
+                        Some(TokenValue::Symbol(Symbol::BraceOpen | Symbol::SemiColon))
+                    );
+                    tag = Some((kind, ident.to_string(), declared));
+                }
+            }
+            TokenValue::Identifier(ident) if depth == 0 && !function => {
+                name = Some(ident.to_string());
+            }
+            TokenValue::Symbol(Symbol::ParenthesisOpen) => {
+                let after_name = idx >= 2
+                    && matches!(
+                        tokens.get(idx - 2).map(Token::get_value),
+                        Some(TokenValue::Identifier(_))
+                    );
+                if depth == 0 && after_name && name.is_some() {
+                    function = true;
+                }
+                depth += 1;
+            }
+            TokenValue::Symbol(Symbol::ParenthesisClose) => depth = depth.saturating_sub(1),
+            TokenValue::Symbol(Symbol::BraceOpen) if depth == 0 => {
+                idx = skip_braces(tokens, idx);
+                if function {
+                    break;
+                }
+            }
+            TokenValue::Symbol(Symbol::SemiColon) if depth == 0 => break,
+            TokenValue::Symbol(Symbol::Assign | Symbol::Comma) if depth == 0 => {
+                // skips the initialiser and the other declarators
+                while let Some(next) = tokens.get(idx) {
+                    idx += 1;
+                    match next.get_value() {
+                        TokenValue::Symbol(Symbol::BraceOpen) => idx = skip_braces(tokens, idx),
+                        TokenValue::Symbol(Symbol::SemiColon) => break,
+                        TokenValue::Char(_)
+                        | TokenValue::Comment(_)
+                        | TokenValue::Error(_)
+                        | TokenValue::HeaderName(_)
+                        | TokenValue::Identifier(_)
+                        | TokenValue::Keyword(_)
+                        | TokenValue::Number(_)
+                        | TokenValue::Pragma(_)
+                        | TokenValue::Str(..)
+                        | TokenValue::Symbol(_) => (),
+                    }
+                }
+                break;
+            }
+            TokenValue::Char(_)
+            | TokenValue::Comment(_)
+            | TokenValue::Error(_)
+            | TokenValue::HeaderName(_)
+            | TokenValue::Identifier(_)
+            | TokenValue::Keyword(_)
+            | TokenValue::Number(_)
+            | TokenValue::Pragma(_)
+            | TokenValue::Str(..)
+            | TokenValue::Symbol(_) => (),
+        }
+    }
+    let mut declared = vec![];
+    if let Some((kind, tag_name, tag_declared)) = tag {
+        if tag_declared || typedef {
+            declared.push((kind, tag_name));
+        }
+    }
+    if let Some(declared_name) = name {
+        let kind = if function {
+            DeclarationKind::Function
+        } else if typedef {
+            DeclarationKind::Typedef
+        } else {
+            DeclarationKind::Variable
+        };
+        declared.push((kind, declared_name));
+    }
+    (idx, declared)
+}
+
+/// Attaches the Doxygen comments to the next declaration, or to the previous
+/// one for `///<`. A blank line detaches them, and the comments are only there
+/// when lexed with `keep_comments`.
+pub fn extract_docs(tokens: &[Token], source_map: &SourceMap) -> DocIndex {
+    let mut index = DocIndex::default();
+    let mut pending = String::new();
+    // names of the previous declaration, with the text of its trailing comments
+    let mut previous: Vec<(DeclarationKind, String)> = vec![];
+    let mut trailing = String::new();
+    let mut idx = 0;
+    while let Some(token) = tokens.get(idx) {
+        idx += 1;
+        if let TokenValue::Comment(comment) = token.get_value() {
+            match doc_text(comment) {
+                Some((text, true)) => {
+                    trailing.push_str(text);
+                    trailing.push('\n');
+                    for declaration in &previous {
+                        index
+                            .docs
+                            .insert(declaration.clone(), Documentation::parse(&trailing));
+                    }
+                }
+                Some((text, false)) => {
+                    pending.push_str(text);
+                    pending.push('\n');
+                }
+                None => (),
+            }
+            if tokens
+                .get(idx)
+                .is_some_and(|next| detached(token.get_location(), next.get_location(), source_map))
+            {
+                pending.clear();
+            }
+            continue;
+        }
+        let (next, declared) = read_declaration(tokens, idx - 1);
+        idx = next;
+        if !pending.is_empty() {
+            for declaration in &declared {
+                index
+                    .docs
+                    .insert(declaration.clone(), Documentation::parse(&pending));
+            }
+            pending.clear();
+        }
+        previous = declared;
+        trailing.clear();
+    }
+    index
+}
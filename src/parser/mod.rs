@@ -1,9 +1,10 @@
+pub mod docs;
 mod state;
 mod symbols;
 mod tree;
 use crate::as_error;
 use crate::errors::compile::Res;
-use crate::errors::{compile::CompileError, location::Location};
+use crate::errors::{compile::CompileError, span::Span};
 use crate::lexer::api::tokens_types::{Symbol, Token, TokenValue};
 extern crate alloc;
 use alloc::vec::IntoIter;
@@ -14,7 +15,7 @@ use tree::{Literal, Node};
 fn handle_literal(
     current: &mut Node,
     leaf: Literal,
-    location: Location,
+    location: Span,
     p_state: &mut ParsingState,
     tokens: &mut IntoIter<Token>,
 ) -> Result<(), CompileError> {
@@ -36,13 +37,25 @@ fn parse_block(
                 handle_literal(current, Literal::Char(ch), location, p_state, tokens)?;
             }
             TokenValue::Identifier(val) => {
-                handle_literal(current, Literal::Variable(val), location, p_state, tokens)?;
+                handle_literal(
+                    current,
+                    Literal::Variable(val.into_owned()),
+                    location,
+                    p_state,
+                    tokens,
+                )?;
             }
             TokenValue::Number(nb) => {
                 handle_literal(current, Literal::Number(nb), location, p_state, tokens)?;
             }
-            TokenValue::Str(val) => {
-                handle_literal(current, Literal::Str(val), location, p_state, tokens)?;
+            TokenValue::Str(_, val) => {
+                handle_literal(
+                    current,
+                    Literal::Str(val.into_lossy_text()),
+                    location,
+                    p_state,
+                    tokens,
+                )?;
             }
             TokenValue::Symbol(Symbol::Colon) if p_state.wanting_colon => {
                 p_state.wanting_colon = false;
@@ -50,6 +63,12 @@ fn parse_block(
             TokenValue::Symbol(symbol) => {
                 handle_symbol(&symbol, current, p_state, tokens, location)?;
             }
+            TokenValue::Comment(_)
+            | TokenValue::Error(_)
+            | TokenValue::HeaderName(_)
+            | TokenValue::Pragma(_) => {
+                parse_block(tokens, p_state, current)?;
+            }
             TokenValue::Keyword(_) => todo!(),
         }
     }
@@ -4,7 +4,7 @@ use super::tree::unary::UnaryOperator;
 use super::tree::Node;
 use crate::as_error;
 use crate::errors::compile::CompileError;
-use crate::errors::location::Location;
+use crate::errors::span::Span;
 use crate::lexer::api::tokens_types::{Symbol, Token};
 use crate::parser::parse_block;
 use crate::parser::tree::{Ternary, TernaryOperator};
@@ -96,7 +96,7 @@ fn handle_one_symbol(
         Star => current
             .push_op(BOp::Multiply)
             .unwrap_or(current.push_op(UOp::Indirection)?),
-        // ternary (only ternary because trigraphs are ignored, and colon is sorted in main function in mod.rs)
+        // ternary (only ternary because trigraphs are replaced before lexing, and colon is sorted in main function in mod.rs)
         Interrogation => {
             let old_node = mem::take(current);
             *current = Node::Ternary(Ternary {
@@ -108,6 +108,11 @@ fn handle_one_symbol(
             p_state.ternary += 1;
         }
         Colon => handle_colon(current, p_state)?,
+        Hash | HashHash => {
+            return Err("Found preprocessor symbol outside of a preprocessor directive.")
+        }
+        ColonColon => return Err("Attributes are not supported yet."),
+        Ellipsis => return Err("Variadic functions are not supported yet."),
         //
         SemiColon => return Ok(false),
         Comma => todo!(),
@@ -136,7 +141,7 @@ pub fn handle_symbol(
     current: &mut Node,
     p_state: &mut ParsingState,
     tokens: &mut IntoIter<Token>,
-    location: Location,
+    location: Span,
 ) -> Result<(), CompileError> {
     if handle_one_symbol(symbol, current, p_state).map_err(|err| as_error!(location, "{err}"))? {
         parse_block(tokens, p_state, current)
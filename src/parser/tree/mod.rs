@@ -5,7 +5,7 @@ pub mod unary;
 use binary::Binary;
 use unary::Unary;
 
-use crate::lexer::api::types::Number;
+use crate::lexer::api::{tokens_types::CharConstant, types::Number};
 
 pub trait Operator: fmt::Debug {
     fn precedence(&self) -> u32;
@@ -72,7 +72,7 @@ pub enum Literal {
     Empty,
     String(String),
     Variable(String),
-    Char(char),
+    Char(CharConstant),
     Str(String),
     Number(Number),
 }
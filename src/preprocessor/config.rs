@@ -0,0 +1,53 @@
+use super::macros::target_macros;
+use crate::lexer::api::{lexing_options::LexingOptions, tokens_types::TokenValue};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default)]
+pub struct PreprocessorConfig {
+    defines: HashMap<String, Vec<TokenValue<'static>>>,
+    include_dirs: Vec<PathBuf>,
+    /// Options the source is lexed with, whose standard sets `__STDC_VERSION__`.
+    lexing: LexingOptions,
+}
+
+impl PreprocessorConfig {
+    pub fn add_include_dir(&mut self, dir: PathBuf) {
+        self.include_dirs.push(dir);
+    }
+
+    pub fn define(&mut self, name: &str, value: Vec<TokenValue<'static>>) {
+        self.defines.insert(name.to_owned(), value);
+    }
+
+    /// Defines the macros describing the target (`__x86_64__`, `__SIZEOF_LONG__`).
+    pub fn define_target_macros(&mut self) {
+        for (name, number) in target_macros() {
+            self.define(name, vec![TokenValue::Number(number)]);
+        }
+    }
+
+    /// Searches `"header.h"` next to the including file, then in the include
+    /// directories like `<header.h>`.
+    pub fn find_include(&self, name: &str, current_file: &str, angled: bool) -> Option<PathBuf> {
+        let local = (!angled)
+            .then(|| Path::new(current_file).parent().map(|dir| dir.join(name)))
+            .flatten();
+        local
+            .into_iter()
+            .chain(self.include_dirs.iter().map(|dir| dir.join(name)))
+            .find(|path| path.exists() && !path.is_dir())
+    }
+
+    pub fn get_define(&self, name: &str) -> Option<&Vec<TokenValue<'static>>> {
+        self.defines.get(name)
+    }
+
+    pub const fn lexing_options(&self) -> &LexingOptions {
+        &self.lexing
+    }
+
+    pub const fn set_lexing_options(&mut self, options: LexingOptions) {
+        self.lexing = options;
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+
+/// Width after which the dependencies are continued on a new line.
+const MAX_LINE_WIDTH: usize = 76;
+
+fn escape(name: &str) -> String {
+    let mut escaped = String::new();
+    for ch in name.chars() {
+        match ch {
+            ' ' | '#' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            '$' => escaped.push_str("$$"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Object file of `source`, the target when there is no `-MT`.
+pub fn default_target(source: &str) -> String {
+    Path::new(source)
+        .with_extension("o")
+        .file_name()
+        .map_or_else(
+            || format!("{source}.o"),
+            |name| name.to_string_lossy().into_owned(),
+        )
+}
+
+/// Writes a Makefile rule making `target` depend on `dependencies`. With
+/// `phony_targets` (`-MP`), each header also gets an empty rule.
+pub fn make_rule(target: &str, dependencies: &[PathBuf], phony_targets: bool) -> String {
+    let mut rule = format!("{target}:");
+    let mut width = rule.len();
+    for dependency in dependencies {
+        let name = escape(&dependency.to_string_lossy());
+        if width + 1 + name.len() > MAX_LINE_WIDTH {
+            rule.push_str(" \\\n");
+            width = 0;
+        }
+        rule.push(' ');
+        rule.push_str(&name);
+        width += 1 + name.len();
+    }
+    rule.push('\n');
+    if phony_targets {
+        for dependency in dependencies.iter().skip(1) {
+            rule.push('\n');
+            rule.push_str(&escape(&dependency.to_string_lossy()));
+            rule.push_str(":\n");
+        }
+    }
+    rule
+}
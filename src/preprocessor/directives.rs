@@ -0,0 +1,209 @@
+use super::config::PreprocessorConfig;
+use super::embed::embed_directive;
+use super::macros::{expect_symbol, Tokens};
+use super::state::PreprocessorState;
+use crate::errors::{compile::CompileError, source_map::SourceMap, span::Span};
+use crate::lexer::api::{
+    tokens_types::{Encoding, StrValue, Symbol, Token, TokenValue},
+    types::Number,
+};
+use crate::{to_error, to_warning};
+use core::str::SplitWhitespace;
+use std::path::Path;
+extern crate alloc;
+use alloc::vec::IntoIter;
+
+#[allow(clippy::match_same_arms)]
+pub fn number_to_usize(number: &Number) -> Option<usize> {
+    match *number {
+        Number::Int(nb) => usize::try_from(nb).ok(),
+        Number::Long(nb) => usize::try_from(nb).ok(),
+        Number::LongLong(nb) => usize::try_from(nb).ok(),
+        Number::UInt(nb) => usize::try_from(nb).ok(),
+        Number::ULong(nb) => usize::try_from(nb).ok(),
+        Number::ULongLong(nb) => usize::try_from(nb).ok(),
+        Number::Float(_) | Number::Double(_) | Number::LongDouble(_) => None,
+    }
+}
+
+/// Handles `#line 42 "file.c"`, and the `# 42 "file.c" flags` linemarkers.
+fn line_directive(
+    line_number: Option<TokenValue>,
+    mut tokens: IntoIter<Token>,
+    location: &Span,
+    source_map: &mut SourceMap,
+    linemarker: bool,
+) -> Result<(), CompileError> {
+    let line = match line_number {
+        Some(TokenValue::Number(number)) => number_to_usize(&number).filter(|line| *line > 0),
+        Some(_) | None => None,
+    }
+    .ok_or_else(|| to_error!(location, "Expected a positive line number after '#line'."))?;
+    let file = match tokens.next().map(Token::into_value) {
+        Some(TokenValue::Str(Encoding::Ordinary, StrValue::Text(file))) => Some(file.into_owned()),
+        None => None,
+        Some(_) => {
+            return Err(to_error!(
+                location,
+                "Expected a file name after the line number in '#line' directive."
+            ))
+        }
+    };
+    if !linemarker && tokens.next().is_some() {
+        return Err(to_error!(
+            location,
+            "Unexpected tokens after the file name in '#line' directive."
+        ));
+    }
+    source_map.add_line_marker(*location, line, file);
+    Ok(())
+}
+
+fn stdc_pragma(mut words: SplitWhitespace, location: &Span) -> Result<(), CompileError> {
+    let name = words.next().ok_or_else(|| {
+        to_warning!(
+            location,
+            "Missing pragma name after 'STDC'. Pragma ignored."
+        )
+    })?;
+    let switch = words.next();
+    let valid = match name {
+        "FP_CONTRACT" | "FENV_ACCESS" | "CX_LIMITED_RANGE" => {
+            matches!(switch, Some("ON" | "OFF" | "DEFAULT"))
+        }
+        "FENV_ROUND" => matches!(
+            switch,
+            Some(
+                "FE_DOWNWARD"
+                    | "FE_TONEAREST"
+                    | "FE_TONEARESTFROMZERO"
+                    | "FE_TOWARDZERO"
+                    | "FE_UPWARD"
+                    | "FE_DYNAMIC"
+            )
+        ),
+        "FENV_DEC_ROUND" => matches!(
+            switch,
+            Some(
+                "FE_DEC_DOWNWARD"
+                    | "FE_DEC_TONEAREST"
+                    | "FE_DEC_TONEARESTFROMZERO"
+                    | "FE_DEC_TOWARDZERO"
+                    | "FE_DEC_UPWARD"
+                    | "FE_DEC_DYNAMIC"
+            )
+        ),
+        _ => {
+            return Err(to_warning!(
+                location,
+                "Unknown pragma 'STDC {name}'. Pragma ignored."
+            ))
+        }
+    };
+    if valid && words.next().is_none() {
+        Ok(())
+    } else {
+        Err(to_warning!(
+            location,
+            "Invalid value for pragma 'STDC {name}'. Pragma ignored."
+        ))
+    }
+}
+
+/// Returns the pragma token to be passed through, if any.
+fn handle_pragma(
+    text: String,
+    location: &Span,
+    source_map: &SourceMap,
+    state: &mut PreprocessorState,
+) -> Result<Option<TokenValue<'static>>, CompileError> {
+    let mut words = text.split_whitespace();
+    match words.next() {
+        Some("once") => {
+            state.set_once(Path::new(source_map.name(location.file_id())));
+            return Ok(None);
+        }
+        Some("STDC") => stdc_pragma(words, location)?,
+        Some(_) | None => (),
+    }
+    Ok(Some(TokenValue::Pragma(text)))
+}
+
+pub fn pragma_operator(
+    tokens: &mut Tokens,
+    location: &Span,
+    source_map: &SourceMap,
+    state: &mut PreprocessorState,
+) -> Result<Option<TokenValue<'static>>, CompileError> {
+    expect_symbol(tokens, &Symbol::ParenthesisOpen, "_Pragma", location)?;
+    let Some(TokenValue::Str(_, StrValue::Text(text))) = tokens.next().map(Token::into_value)
+    else {
+        return Err(to_error!(
+            location,
+            "Expected a string literal in '_Pragma' operator."
+        ));
+    };
+    expect_symbol(tokens, &Symbol::ParenthesisClose, "_Pragma", location)?;
+    handle_pragma(text.into_owned(), location, source_map, state)
+}
+
+/// Handles the directive after a `#`, and returns its replacement.
+pub fn handle_directive<'src>(
+    tokens: Vec<Token<'src>>,
+    location: &Span,
+    source_map: &mut SourceMap,
+    config: &PreprocessorConfig,
+    state: &mut PreprocessorState,
+) -> Result<Vec<TokenValue<'src>>, CompileError> {
+    // the directive may be continued on the next lines
+    let directive = location.to(tokens.last().map_or(*location, |last| *last.get_location()));
+    let mut tokens_iter = tokens.into_iter();
+    let Some(first) = tokens_iter.next() else {
+        return Ok(vec![]);
+    };
+    match first.into_value() {
+        TokenValue::Identifier(name) if name == "line" => {
+            let line_number = tokens_iter.next().map(Token::into_value);
+            line_directive(line_number, tokens_iter, &directive, source_map, false)?;
+        }
+        number @ TokenValue::Number(_) => {
+            line_directive(Some(number), tokens_iter, &directive, source_map, true)?;
+        }
+        TokenValue::Identifier(name) if name == "pragma" => {
+            let text = tokens_iter
+                .map(|token| token.get_value().to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            return Ok(handle_pragma(text, location, source_map, state)?.into_iter().collect());
+        }
+        TokenValue::Identifier(name) if name == "embed" => {
+            return embed_directive(tokens_iter.collect(), location, source_map, config, state);
+        }
+        TokenValue::Identifier(name) => {
+            return Err(to_error!(
+                location,
+                "Preprocessor directive '#{name}' is not supported."
+            ))
+        }
+        TokenValue::Keyword(keyword) => {
+            return Err(to_error!(
+                location,
+                "Preprocessor directive '#{}' is not supported.",
+                keyword.repr()
+            ))
+        }
+        value @ (TokenValue::Char(..)
+        | TokenValue::Comment(_)
+        | TokenValue::Error(_)
+        | TokenValue::HeaderName(_)
+        | TokenValue::Pragma(_)
+        | TokenValue::Str(..)
+        | TokenValue::Symbol(_)) => {
+            return Err(to_error!(
+                location,
+                "Invalid preprocessor directive: expected a directive name after '#', but found '{value}'."
+            ))
+        }
+    }
+    Ok(vec![])
+}
@@ -0,0 +1,156 @@
+use super::config::PreprocessorConfig;
+use super::directives::number_to_usize;
+use super::macros::{header_name, Tokens};
+use super::state::PreprocessorState;
+use crate::errors::{compile::CompileError, source_map::SourceMap, span::Span};
+use crate::lexer::api::{
+    tokens_types::{Symbol, Token, TokenValue},
+    types::Number,
+};
+use crate::to_error;
+use std::fs::File;
+use std::io::Read as _;
+
+#[derive(Default)]
+struct EmbedParameters<'src> {
+    limit: Option<usize>,
+    prefix: Vec<TokenValue<'src>>,
+    suffix: Vec<TokenValue<'src>>,
+    if_empty: Option<Vec<TokenValue<'src>>>,
+}
+
+fn parameter_tokens<'src>(
+    tokens: &mut Tokens<'src>,
+    name: &str,
+    location: &Span,
+) -> Result<Vec<TokenValue<'src>>, CompileError> {
+    if tokens.next().map(Token::into_value) != Some(TokenValue::Symbol(Symbol::ParenthesisOpen)) {
+        return Err(to_error!(
+            location,
+            "Expected '(' after #embed parameter '{name}'."
+        ));
+    }
+    let mut depth: usize = 0;
+    let mut values = vec![];
+    loop {
+        match tokens.next().map(Token::into_value) {
+            Some(TokenValue::Symbol(Symbol::ParenthesisClose)) if depth == 0 => return Ok(values),
+            Some(value @ TokenValue::Symbol(Symbol::ParenthesisOpen)) => {
+                depth += 1;
+                values.push(value);
+            }
+            Some(value @ TokenValue::Symbol(Symbol::ParenthesisClose)) => {
+                depth -= 1;
+                values.push(value);
+            }
+            Some(value) => values.push(value),
+            None => {
+                return Err(to_error!(
+                    location,
+                    "Missing ')' to close #embed parameter '{name}'."
+                ))
+            }
+        }
+    }
+}
+
+fn parse_limit(values: &[TokenValue], location: &Span) -> Result<usize, CompileError> {
+    let limit = match values {
+        [TokenValue::Number(number)] => number_to_usize(number),
+        _ => None,
+    };
+    limit.ok_or_else(|| {
+        to_error!(
+            location,
+            "#embed parameter 'limit' expects a non-negative integer constant."
+        )
+    })
+}
+
+fn parse_parameters<'src>(
+    tokens: &mut Tokens<'src>,
+    location: &Span,
+) -> Result<EmbedParameters<'src>, CompileError> {
+    let mut parameters = EmbedParameters::default();
+    while let Some(token) = tokens.next() {
+        let name = match token.into_value() {
+            TokenValue::Identifier(name) => name,
+            value @ (TokenValue::Char(..)
+            | TokenValue::Comment(_)
+            | TokenValue::Error(_)
+            | TokenValue::HeaderName(_)
+            | TokenValue::Keyword(_)
+            | TokenValue::Number(_)
+            | TokenValue::Pragma(_)
+            | TokenValue::Str(..)
+            | TokenValue::Symbol(_)) => {
+                return Err(to_error!(
+                    location,
+                    "Expected an #embed parameter name, found '{value}'."
+                ))
+            }
+        };
+        if tokens.peek().map(Token::get_value) == Some(&TokenValue::Symbol(Symbol::ColonColon)) {
+            return Err(to_error!(
+                location,
+                "Vendor #embed parameter '{name}::' is not supported."
+            ));
+        }
+        let standard_name = name
+            .strip_prefix("__")
+            .and_then(|stripped| stripped.strip_suffix("__"))
+            .unwrap_or(&name);
+        let values = parameter_tokens(tokens, standard_name, location)?;
+        match standard_name {
+            "limit" => parameters.limit = Some(parse_limit(&values, location)?),
+            "prefix" => parameters.prefix = values,
+            "suffix" => parameters.suffix = values,
+            "if_empty" => parameters.if_empty = Some(values),
+            _ => return Err(to_error!(location, "Unknown #embed parameter '{name}'.")),
+        }
+    }
+    Ok(parameters)
+}
+
+/// Handles `#embed`, whose file becomes a comma-separated list of integers.
+pub fn embed_directive<'src>(
+    tokens: Vec<Token<'src>>,
+    location: &Span,
+    source_map: &SourceMap,
+    config: &PreprocessorConfig,
+    state: &mut PreprocessorState,
+) -> Result<Vec<TokenValue<'src>>, CompileError> {
+    let mut tokens_iter = tokens.into_iter().peekable();
+    let (name, angled) = header_name(&mut tokens_iter, location)?;
+    let parameters = parse_parameters(&mut tokens_iter, location)?;
+    let path = config
+        .find_include(&name, source_map.name(location.file_id()), angled)
+        .ok_or_else(|| to_error!(location, "Embedded file '{name}' not found."))?;
+    // only the bytes kept are read, as the resource may be unbounded
+    let limit = parameters
+        .limit
+        .map_or(u64::MAX, |limit| u64::try_from(limit).unwrap_or(u64::MAX));
+    let mut content = vec![];
+    File::open(&path)
+        .and_then(|file| file.take(limit).read_to_end(&mut content))
+        .map_err(|err| {
+            to_error!(
+                location,
+                "Failed to read embedded file '{}': {err}",
+                path.display()
+            )
+        })?;
+    state.add_dependency(path);
+    if content.is_empty() {
+        return Ok(parameters.if_empty.unwrap_or_default());
+    }
+    let mut values = parameters.prefix;
+    for (idx, byte) in content.into_iter().enumerate() {
+        if idx != 0 {
+            values.push(TokenValue::Symbol(Symbol::Comma));
+        }
+        values.push(TokenValue::Number(Number::Int(byte.into())));
+    }
+    values.extend(parameters.suffix);
+    Ok(values)
+}
@@ -0,0 +1,97 @@
+use super::config::PreprocessorConfig;
+use super::macros::header_name;
+use super::preprocess;
+use super::state::PreprocessorState;
+use crate::errors::{
+    compile::{CompileError, Res},
+    source_map::SourceMap,
+    span::Span,
+};
+use crate::lexer::{api::tokens_types::Token, decode::decode_file, lex_file};
+use crate::to_error;
+use std::fs;
+use std::path::PathBuf;
+
+/// Maximum nesting of `#include` directives, to stop on recursive inclusions.
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+/// Returns the path of the included file, or `None` for a `#pragma once` file
+/// already included.
+fn included_path(
+    tokens: Vec<Token>,
+    location: &Span,
+    source_map: &SourceMap,
+    config: &PreprocessorConfig,
+    state: &PreprocessorState,
+) -> Result<Option<PathBuf>, CompileError> {
+    let mut tokens_iter = tokens.into_iter().peekable();
+    let (name, angled) = header_name(&mut tokens_iter, location)?;
+    if tokens_iter.next().is_some() {
+        return Err(to_error!(
+            location,
+            "Unexpected tokens after the header name in '#include' directive."
+        ));
+    }
+    let path = config
+        .find_include(&name, source_map.name(location.file_id()), angled)
+        .ok_or_else(|| to_error!(location, "Included file '{name}' not found."))?;
+    if state.is_once(&path) {
+        return Ok(None);
+    }
+    if state.include_depth() >= MAX_INCLUDE_DEPTH {
+        return Err(to_error!(
+            location,
+            "'#include' nested more than {MAX_INCLUDE_DEPTH} times: the inclusions are probably recursive."
+        ));
+    }
+    Ok(Some(path))
+}
+
+pub fn include_directive(
+    tokens: Vec<Token>,
+    location: &Span,
+    source_map: &mut SourceMap,
+    config: &PreprocessorConfig,
+    state: &mut PreprocessorState,
+) -> Res<Vec<Token<'static>>> {
+    let path = match included_path(tokens, location, source_map, config, state) {
+        Ok(Some(path)) => path,
+        Ok(None) => return Res::from(vec![]),
+        Err(err) => return Res::from((vec![], vec![err])),
+    };
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            let error = to_error!(
+                location,
+                "Failed to read included file '{}': {err}",
+                path.display()
+            );
+            return Res::from((vec![], vec![error]));
+        }
+    };
+    let name = path.to_string_lossy().into_owned();
+    state.add_dependency(path);
+    let options = config.lexing_options();
+    let Res {
+        result: file_id,
+        mut errors,
+    } = decode_file(source_map, name, &bytes, options);
+    let source = source_map.source(file_id);
+    let Res {
+        result: lexed,
+        errors: lexer_errors,
+    } = lex_file(&source, file_id, options);
+    errors.extend(lexer_errors);
+    state.set_include_depth(state.include_depth() + 1);
+    let Res {
+        result: preprocessed,
+        errors: preprocessor_errors,
+    } = preprocess(lexed, source_map, config, state);
+    state.set_include_depth(state.include_depth() - 1);
+    errors.extend(preprocessor_errors);
+    Res::from((
+        preprocessed.into_iter().map(Token::into_owned).collect(),
+        errors,
+    ))
+}
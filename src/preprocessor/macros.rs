@@ -0,0 +1,221 @@
+use super::config::PreprocessorConfig;
+use super::state::PreprocessorState;
+use crate::errors::{compile::CompileError, source_map::SourceMap, span::Span};
+#[allow(clippy::wildcard_imports)]
+use crate::lexer::api::types::arch_types::*;
+use crate::lexer::api::{
+    standard::Standard,
+    tokens_types::{Encoding, StrValue, Symbol, Token, TokenValue},
+    types::Number,
+};
+use crate::to_error;
+use core::iter::Peekable;
+use core::mem;
+extern crate alloc;
+use alloc::vec::IntoIter;
+
+pub type Tokens<'src> = Peekable<IntoIter<Token<'src>>>;
+
+pub const fn stdc_version(standard: Standard) -> Option<Long> {
+    match standard {
+        Standard::C89 => None,
+        Standard::C99 => Some(199_901),
+        Standard::C11 => Some(201_112),
+        Standard::C17 => Some(201_710),
+        Standard::C23 => Some(202_311),
+    }
+}
+
+fn size_of_type<T>() -> Number {
+    Number::Int(Int::try_from(mem::size_of::<T>()).expect("type sizes fit in an int"))
+}
+
+pub fn target_macros() -> Vec<(&'static str, Number)> {
+    let mut macros = vec![
+        ("__CHAR_BIT__", Number::Int(8)),
+        ("__SIZEOF_INT__", size_of_type::<Int>()),
+        ("__SIZEOF_LONG__", size_of_type::<Long>()),
+        ("__SIZEOF_LONG_LONG__", size_of_type::<LongLong>()),
+        ("__SIZEOF_FLOAT__", size_of_type::<Float>()),
+        ("__SIZEOF_DOUBLE__", size_of_type::<Double>()),
+        ("__SIZEOF_LONG_DOUBLE__", size_of_type::<LongDouble>()),
+        ("__SIZEOF_POINTER__", size_of_type::<usize>()),
+    ];
+    let data_model: &[&'static str] = if mem::size_of::<Long>() == 8 && mem::size_of::<usize>() == 8
+    {
+        &["__LP64__", "_LP64"]
+    } else if mem::size_of::<Long>() == 4 && mem::size_of::<usize>() == 4 {
+        &["__ILP32__", "_ILP32"]
+    } else {
+        &[]
+    };
+    let arch: &[&'static str] = if cfg!(target_arch = "x86_64") {
+        &["__x86_64__", "__x86_64", "__amd64__", "__amd64"]
+    } else if cfg!(target_arch = "x86") {
+        &["__i386__", "__i386"]
+    } else if cfg!(target_arch = "aarch64") {
+        &["__aarch64__"]
+    } else if cfg!(target_arch = "arm") {
+        &["__arm__"]
+    } else if cfg!(target_arch = "riscv64") {
+        &["__riscv"]
+    } else {
+        &[]
+    };
+    macros.extend(
+        data_model
+            .iter()
+            .chain(arch)
+            .map(|name| (*name, Number::Int(1))),
+    );
+    macros
+}
+
+fn next_is_symbol(tokens: &mut Tokens, symbol: &Symbol) -> bool {
+    tokens
+        .next_if(|token| matches!(token.get_value(), TokenValue::Symbol(found) if found == symbol))
+        .is_some()
+}
+
+pub fn expect_symbol(
+    tokens: &mut Tokens,
+    symbol: &Symbol,
+    macro_name: &str,
+    location: &Span,
+) -> Result<(), CompileError> {
+    if next_is_symbol(tokens, symbol) {
+        Ok(())
+    } else {
+        Err(to_error!(
+            location,
+            "Expected '{}' in '{macro_name}' operator.",
+            symbol.repr()
+        ))
+    }
+}
+
+/// Reads a `"file"` or `<file>` header name, and whether it was angled.
+pub fn header_name(tokens: &mut Tokens, location: &Span) -> Result<(String, bool), CompileError> {
+    match tokens.next().map(Token::into_value) {
+        Some(TokenValue::Str(Encoding::Ordinary, StrValue::Text(name))) => {
+            Ok((name.into_owned(), false))
+        }
+        Some(TokenValue::HeaderName(name)) => Ok((name.into_owned(), true)),
+        // the lexer found no `>` on the line
+        Some(TokenValue::Symbol(Symbol::Lt)) => Err(to_error!(
+            location,
+            "Invalid header name: missing '>' to close '<'."
+        )),
+        Some(_) | None => Err(to_error!(
+            location,
+            "Expected a header name, of the form \"file\" or <file>."
+        )),
+    }
+}
+
+fn has_include(
+    tokens: &mut Tokens,
+    location: &Span,
+    source_map: &SourceMap,
+    config: &PreprocessorConfig,
+) -> Result<TokenValue<'static>, CompileError> {
+    expect_symbol(tokens, &Symbol::ParenthesisOpen, "__has_include", location)?;
+    let (name, angled) = header_name(tokens, location)?;
+    expect_symbol(tokens, &Symbol::ParenthesisClose, "__has_include", location)?;
+    let found = config
+        .find_include(&name, source_map.name(location.file_id()), angled)
+        .is_some();
+    Ok(TokenValue::Number(Number::Int(Int::from(found))))
+}
+
+fn c_attribute_version(name: &str) -> Long {
+    match name
+        .strip_prefix("__")
+        .and_then(|stripped| stripped.strip_suffix("__"))
+        .unwrap_or(name)
+    {
+        "deprecated" | "fallthrough" | "maybe_unused" => 201_904,
+        "nodiscard" => 202_003,
+        "noreturn" | "_Noreturn" => 202_202,
+        "reproducible" | "unsequenced" => 202_207,
+        _ => 0,
+    }
+}
+
+fn attribute_name_part(tokens: &mut Tokens, location: &Span) -> Result<String, CompileError> {
+    match tokens.next().map(Token::into_value) {
+        Some(TokenValue::Identifier(ident)) => Ok(ident.into_owned()),
+        Some(TokenValue::Keyword(keyword)) => Ok(keyword.repr().to_owned()),
+        Some(_) | None => Err(to_error!(
+            location,
+            "Expected an attribute name in '__has_c_attribute' operator."
+        )),
+    }
+}
+
+fn has_c_attribute(
+    tokens: &mut Tokens,
+    location: &Span,
+) -> Result<TokenValue<'static>, CompileError> {
+    expect_symbol(
+        tokens,
+        &Symbol::ParenthesisOpen,
+        "__has_c_attribute",
+        location,
+    )?;
+    let prefix = attribute_name_part(tokens, location)?;
+    // `::` is lexed as two `:` before C23
+    let vendor = next_is_symbol(tokens, &Symbol::ColonColon)
+        || (next_is_symbol(tokens, &Symbol::Colon) && next_is_symbol(tokens, &Symbol::Colon));
+    let version = if vendor {
+        attribute_name_part(tokens, location)?;
+        // no vendor attribute is supported
+        0
+    } else {
+        c_attribute_version(&prefix)
+    };
+    expect_symbol(
+        tokens,
+        &Symbol::ParenthesisClose,
+        "__has_c_attribute",
+        location,
+    )?;
+    Ok(TokenValue::Number(Number::Long(version)))
+}
+
+/// Returns `Ok(None)` if `name` isn't a predefined or dynamic macro.
+pub fn expand_builtin(
+    name: &str,
+    location: &Span,
+    source_map: &SourceMap,
+    tokens: &mut Tokens,
+    config: &PreprocessorConfig,
+    state: &mut PreprocessorState,
+) -> Result<Option<TokenValue<'static>>, CompileError> {
+    Ok(Some(match name {
+        "__FILE__" => TokenValue::Str(
+            Encoding::Ordinary,
+            StrValue::Text(source_map.presumed(*location).0.to_owned().into()),
+        ),
+        "__LINE__" => TokenValue::Number(Number::Int(
+            Int::try_from(source_map.presumed(*location).1).unwrap_or(Int::MAX),
+        )),
+        "__DATE__" => TokenValue::Str(
+            Encoding::Ordinary,
+            StrValue::Text(state.date().to_owned().into()),
+        ),
+        "__TIME__" => TokenValue::Str(
+            Encoding::Ordinary,
+            StrValue::Text(state.time().to_owned().into()),
+        ),
+        "__COUNTER__" => TokenValue::Number(Number::Int(state.next_counter())),
+        "__STDC__" | "__STDC_HOSTED__" => TokenValue::Number(Number::Int(1)),
+        "__STDC_VERSION__" => match stdc_version(config.lexing_options().standard) {
+            Some(version) => TokenValue::Number(Number::Long(version)),
+            None => return Ok(None),
+        },
+        "__has_include" => has_include(tokens, location, source_map, config)?,
+        "__has_c_attribute" => has_c_attribute(tokens, location)?,
+        _ => return Ok(None),
+    }))
+}
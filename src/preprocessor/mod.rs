@@ -0,0 +1,103 @@
+pub mod config;
+pub mod dependencies;
+mod directives;
+mod embed;
+mod include;
+mod macros;
+pub mod output;
+pub mod state;
+use crate::errors::{
+    compile::{CompileError, Res},
+    source_map::SourceMap,
+    span::Span,
+};
+use crate::lexer::api::tokens_types::{Symbol, Token, TokenValue};
+use config::PreprocessorConfig;
+use directives::{handle_directive, pragma_operator};
+use include::include_directive;
+use macros::{expand_builtin, Tokens};
+use state::PreprocessorState;
+
+/// Returns `Ok(None)` if the identifier isn't a macro nor an operator.
+fn expand_identifier<'src>(
+    name: &str,
+    location: &Span,
+    source_map: &SourceMap,
+    tokens: &mut Tokens<'src>,
+    config: &PreprocessorConfig,
+    state: &mut PreprocessorState,
+) -> Result<Option<Vec<TokenValue<'src>>>, CompileError> {
+    if name == "_Pragma" {
+        return pragma_operator(tokens, location, source_map, state)
+            .map(|pragma| Some(pragma.into_iter().collect()));
+    }
+    if let Some(value) = expand_builtin(name, location, source_map, tokens, config, state)? {
+        return Ok(Some(vec![value]));
+    }
+    Ok(config.get_define(name).cloned())
+}
+
+pub fn preprocess<'src>(
+    tokens: Vec<Token<'src>>,
+    source_map: &mut SourceMap,
+    config: &PreprocessorConfig,
+    state: &mut PreprocessorState,
+) -> Res<Vec<Token<'src>>> {
+    let mut errors = vec![];
+    let mut result = Vec::with_capacity(tokens.len());
+    let mut tokens_iter = tokens.into_iter().peekable();
+    while let Some(token) = tokens_iter.next() {
+        let location = *token.get_location();
+        let expanded = match token.get_value() {
+            TokenValue::Symbol(Symbol::Hash) if token.is_first_on_line() => {
+                let mut directive = vec![];
+                while let Some(next) = tokens_iter.next_if(|next| !next.is_first_on_line()) {
+                    if !matches!(next.get_value(), TokenValue::Comment(_)) {
+                        directive.push(next);
+                    }
+                }
+                if matches!(
+                    directive.first().map(Token::get_value),
+                    Some(TokenValue::Identifier(name)) if name == "include"
+                ) {
+                    let Res {
+                        result: included,
+                        errors: include_errors,
+                    } = include_directive(
+                        directive.split_off(1),
+                        &location,
+                        source_map,
+                        config,
+                        state,
+                    );
+                    result.extend(included);
+                    errors.extend(include_errors);
+                    continue;
+                }
+                handle_directive(directive, &location, source_map, config, state).map(Some)
+            }
+            TokenValue::Identifier(name) => {
+                expand_identifier(name, &location, source_map, &mut tokens_iter, config, state)
+            }
+            TokenValue::Char(..)
+            | TokenValue::Comment(_)
+            | TokenValue::Error(_)
+            | TokenValue::HeaderName(_)
+            | TokenValue::Keyword(_)
+            | TokenValue::Number(_)
+            | TokenValue::Pragma(_)
+            | TokenValue::Str(..)
+            | TokenValue::Symbol(_) => Ok(None),
+        };
+        match expanded {
+            Ok(Some(values)) => result.extend(
+                values
+                    .into_iter()
+                    .map(|value| Token::from_value(value, location)),
+            ),
+            Ok(None) => result.push(token),
+            Err(err) => errors.push(err),
+        }
+    }
+    Res::from((result, errors))
+}
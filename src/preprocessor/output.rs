@@ -0,0 +1,133 @@
+use crate::errors::source_map::SourceMap;
+use crate::lexer::api::tokens_types::{Encoding, StrValue, Token, TokenValue};
+use core::fmt::Write as _;
+
+/// Maximum number of empty lines printed before a linemarker is used instead.
+const MAX_EMPTY_LINES: usize = 8;
+
+/// Pairs of characters lexed as a single punctuator.
+const MERGING_PUNCTUATORS: [(char, char); 29] = [
+    ('+', '+'),
+    ('-', '-'),
+    ('-', '>'),
+    ('<', '<'),
+    ('>', '>'),
+    ('<', '='),
+    ('>', '='),
+    ('=', '='),
+    ('!', '='),
+    ('&', '&'),
+    ('|', '|'),
+    ('+', '='),
+    ('-', '='),
+    ('*', '='),
+    ('/', '='),
+    ('%', '='),
+    ('&', '='),
+    ('|', '='),
+    ('^', '='),
+    ('#', '#'),
+    ('/', '/'),
+    ('/', '*'),
+    ('.', '.'),
+    (':', ':'),
+    ('<', ':'),
+    (':', '>'),
+    ('<', '%'),
+    ('%', '>'),
+    ('%', ':'),
+];
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+fn would_merge(previous: &str, next: &str) -> bool {
+    let (Some(last), Some(first)) = (previous.chars().last(), next.chars().next()) else {
+        return false;
+    };
+    let previous_is_number = previous.starts_with(|ch: char| ch.is_ascii_digit());
+    (is_word_char(last) && (is_word_char(first) || matches!(first, '"' | '\'')))
+        || (previous_is_number && matches!(first, '.' | '+' | '-'))
+        || (last == '.' && first.is_ascii_digit())
+        || MERGING_PUNCTUATORS.contains(&(last, first))
+}
+
+fn write_linemarker(output: &mut String, line: usize, file: &str) {
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    writeln!(
+        output,
+        "# {line} {}",
+        TokenValue::Str(Encoding::Ordinary, StrValue::Text(file.into()))
+    )
+    .expect("writing to a String never fails");
+}
+
+/// Writes the tokens as C source, as `-E` does, with linemarkers when the file
+/// changes or when too many lines are skipped.
+pub fn preprocessed_output(tokens: &[Token], source_map: &SourceMap) -> String {
+    let mut output = String::new();
+    // file and line of the output
+    let mut current: Option<(String, usize)> = None;
+    // text of the previous token on the same line
+    let mut previous: Option<String> = None;
+    for token in tokens {
+        let (file, line) = source_map.presumed(*token.get_location());
+        let text = match token.get_value() {
+            TokenValue::Pragma(pragma) => format!("#pragma {pragma}"),
+            TokenValue::Char(..)
+            | TokenValue::Comment(_)
+            | TokenValue::Error(_)
+            | TokenValue::HeaderName(_)
+            | TokenValue::Identifier(_)
+            | TokenValue::Keyword(_)
+            | TokenValue::Number(_)
+            | TokenValue::Str(..)
+            | TokenValue::Symbol(_) => token.get_lexeme().to_owned(),
+        };
+        match &mut current {
+            Some((current_file, current_line))
+                if current_file == file
+                    && *current_line <= line
+                    && line - *current_line <= MAX_EMPTY_LINES =>
+            {
+                if *current_line < line {
+                    output.push_str(&"\n".repeat(line - *current_line));
+                    *current_line = line;
+                    previous = None;
+                }
+            }
+            Some(_) | None => {
+                write_linemarker(&mut output, line, file);
+                current = Some((file.to_owned(), line));
+                previous = None;
+            }
+        }
+        let is_pragma = matches!(token.get_value(), TokenValue::Pragma(_));
+        let output_line = &mut current.as_mut().expect("set above").1;
+        match &previous {
+            // pragmas must be on their own line
+            Some(_) if is_pragma => {
+                output.push('\n');
+                *output_line += 1;
+            }
+            Some(previous_text) if would_merge(previous_text, &text) => output.push(' '),
+            Some(_) | None => (),
+        }
+        output.push_str(&text);
+        *output_line += text.matches('\n').count();
+        if is_pragma {
+            output.push('\n');
+            *output_line += 1;
+            previous = None;
+        } else {
+            previous = Some(text);
+        }
+    }
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
@@ -0,0 +1,116 @@
+use crate::lexer::api::types::arch_types::Int;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+#[derive(Debug)]
+pub struct PreprocessorState {
+    counter: Int,
+    date: String,
+    dependencies: Vec<PathBuf>,
+    include_depth: usize,
+    /// Canonical paths of the files that contained `#pragma once`.
+    once_files: HashSet<PathBuf>,
+    time: String,
+}
+
+impl PreprocessorState {
+    pub fn add_dependency(&mut self, path: PathBuf) {
+        if !self.dependencies.contains(&path) {
+            self.dependencies.push(path);
+        }
+    }
+
+    pub const fn date(&self) -> &String {
+        &self.date
+    }
+
+    /// Files opened during preprocessing, in order.
+    pub fn dependencies(&self) -> &[PathBuf] {
+        &self.dependencies
+    }
+
+    pub const fn include_depth(&self) -> usize {
+        self.include_depth
+    }
+
+    pub fn is_once(&self, file: &Path) -> bool {
+        self.once_files.contains(&canonical(file))
+    }
+
+    pub fn new() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let (date, time) = format_timestamp(secs);
+        Self {
+            counter: 0,
+            date,
+            dependencies: vec![],
+            include_depth: 0,
+            once_files: HashSet::new(),
+            time,
+        }
+    }
+
+    pub const fn next_counter(&mut self) -> Int {
+        let value = self.counter;
+        self.counter += 1i32;
+        value
+    }
+
+    pub const fn set_include_depth(&mut self, depth: usize) {
+        self.include_depth = depth;
+    }
+
+    pub fn set_once(&mut self, file: &Path) {
+        self.once_files.insert(canonical(file));
+    }
+
+    pub const fn time(&self) -> &String {
+        &self.time
+    }
+}
+
+/// Returns the canonical form of the path, or the path if it doesn't exist.
+fn canonical(file: &Path) -> PathBuf {
+    fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf())
+}
+
+/// Formats a UNIX timestamp as `__DATE__` and `__TIME__`, in UTC.
+pub fn format_timestamp(secs: u64) -> (String, String) {
+    let (days, secs_of_day) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    let time = format!(
+        "{:02}:{:02}:{:02}",
+        secs_of_day.div_euclid(3600),
+        secs_of_day.rem_euclid(3600).div_euclid(60),
+        secs_of_day.rem_euclid(60)
+    );
+    // civil date from days since 1970-01-01, shifted to start years on March 1st
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era.div_euclid(1460) + day_of_era.div_euclid(36524)
+        - day_of_era.div_euclid(146_096))
+    .div_euclid(365);
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era.div_euclid(4) - year_of_era.div_euclid(100));
+    let shifted_month = (5 * day_of_year + 2).div_euclid(153);
+    let day = day_of_year - (153 * shifted_month + 2).div_euclid(5) + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 2
+    } else {
+        shifted_month - 10
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 1);
+    let month_name = usize::try_from(month)
+        .ok()
+        .and_then(|idx| MONTHS.get(idx))
+        .expect("month is in 0..12");
+    (format!("{month_name} {day:>2} {year}"), time)
+}
@@ -1,7 +1,35 @@
 use crate::errors::display::display_errors;
-use crate::errors::{compile::Res, location::Location};
+use crate::errors::span::{FileId, LineIndex, Span};
+use crate::errors::{
+    compile::{CompileError, Res},
+    source_map::SourceMap,
+};
 use crate::lexer;
-use std::fs;
+use crate::lexer::api::{
+    lexing_options::LexingOptions,
+    source_encoding::SourceEncoding,
+    standard::Standard,
+    tokens_types::{Encoding, StrValue, Token, TokenValue},
+};
+use crate::lexer::concat::concat_strings;
+use crate::lexer::decode::decode_file;
+use crate::parser::docs::{extract_docs, DeclarationKind, DocIndex, Documentation};
+use crate::preprocessor::{
+    config::PreprocessorConfig,
+    dependencies::make_rule,
+    output::preprocessed_output,
+    preprocess,
+    state::{format_timestamp, PreprocessorState},
+};
+use crate::Args;
+use core::mem;
+use std::path::PathBuf;
+extern crate alloc;
+use alloc::borrow::Cow;
+use core::fmt::Write as _;
+use std::{env, fs};
+extern crate test;
+use test::Bencher;
 
 #[allow(
     clippy::unwrap_used,
@@ -17,13 +45,1613 @@ fn lext_lexer() {
         .filter(|x| x.starts_with("lexer-") && x.ends_with(".c"))
     {
         let path = format!("./data/{file}");
+        let mut source_map = SourceMap::default();
         let content = fs::read_to_string(&path).unwrap();
-        let mut location = Location::from(path.clone());
-        let Res { errors, .. } = lexer::lex_file(&content, &mut location);
+        let file_id = source_map.add_file(path, content);
+        let Res { errors, .. } = lexer::lex_file(
+            source_map.content(file_id),
+            file_id,
+            &LexingOptions::default(),
+        );
         if !errors.is_empty() {
-            display_errors(errors, &[(path, &content)]);
+            display_errors(errors, &source_map);
             panic = true;
         }
     }
     assert!(!panic);
 }
+
+/// Function of [`generated_source`], `IDX` being replaced by its index.
+const GENERATED_FUNCTION: &str = "/// Documentation of f_IDX.
+static const char *name_IDX = \"f\\x41_IDX\\n\"; // trailing comment
+int f_IDX(int a, float b) {
+\tunsigned long n = 0x1Fu + IDXul * 3; /* block
+\t   comment */ double d = 1.5e-3 + b;
+\tif (a >= IDX && n != 'c') \\
+\t\treturn a << 2;
+\treturn (int)(d * 2.0) % 7 + u8\"x\"[0];
+}
+
+";
+
+/// Returns a C source of `nb_functions` functions.
+fn generated_source(nb_functions: usize) -> String {
+    let mut source = String::from("/* generated */\n#include <stdio.h>\n\n");
+    for idx in 0..nb_functions {
+        source.push_str(&GENERATED_FUNCTION.replace("IDX", &idx.to_string()));
+    }
+    source
+}
+
+/// Checks that the tokens and their trivia cover the whole source.
+#[test]
+fn lossless_trivia() {
+    for (source, trigraphs, comments) in [
+        (generated_source(10), false, None),
+        (
+            "a; // c \\\n still comment\nb;".to_owned(),
+            false,
+            Some(["// c \\\n still comment"].as_slice()),
+        ),
+        (
+            "a; /\\\n* c */ b;".to_owned(),
+            false,
+            Some(&["/\\\n* c */"]),
+        ),
+        (
+            "a; ??/\n b; // c ??/\n d\n".to_owned(),
+            true,
+            Some(&["// c ??/\n d"]),
+        ),
+    ] {
+        let options = LexingOptions {
+            trigraphs,
+            trivia: true,
+            ..Default::default()
+        };
+        let Res { result, errors } = lexer::lex_file(&source, FileId::default(), &options);
+        // the trigraphs are warned about
+        assert!(!errors.iter().any(CompileError::is_error), "{errors:?}");
+        let mut rebuilt = String::with_capacity(source.len());
+        let mut found = vec![];
+        for token in &result {
+            for trivia in token.get_leading_trivia() {
+                rebuilt.push_str(trivia.get_location().text(&source));
+            }
+            rebuilt.push_str(token.get_lexeme());
+            for trivia in token.get_trailing_trivia() {
+                rebuilt.push_str(trivia.get_location().text(&source));
+            }
+            found.extend(
+                token
+                    .get_leading_trivia()
+                    .iter()
+                    .chain(token.get_trailing_trivia())
+                    .map(|trivia| trivia.get_location().text(&source))
+                    .filter(|text| text.starts_with('/')),
+            );
+        }
+        assert_eq!(rebuilt, source);
+        if let Some(expected) = comments {
+            assert_eq!(found, expected, "{source:?}");
+        }
+    }
+}
+
+/// Lexes the zlib examples in `./data`.
+#[allow(clippy::unwrap_used, clippy::case_sensitive_file_extension_comparisons)]
+#[bench]
+fn bench_lexer(bencher: &mut Bencher) {
+    let mut source_map = SourceMap::default();
+    let files: Vec<_> = fs::read_dir("./data")
+        .unwrap()
+        .map(|file| file.unwrap().file_name().into_string().unwrap())
+        .filter(|x| x.starts_with("bench-") && x.ends_with(".c"))
+        .map(|file| {
+            let path = format!("./data/{file}");
+            let content = fs::read_to_string(&path).unwrap();
+            source_map.add_file(path, content)
+        })
+        .collect();
+    bencher.bytes = files
+        .iter()
+        .map(|file_id| source_map.content(*file_id).len())
+        .sum::<usize>()
+        .try_into()
+        .unwrap();
+    bencher.iter(|| {
+        for file_id in &files {
+            lexer::lex_file(
+                source_map.content(*file_id),
+                *file_id,
+                &LexingOptions::default(),
+            );
+        }
+    });
+}
+
+/// Checks the tokens of the `./data/bench-*.c` files against those of the
+/// previous lexer.
+#[allow(clippy::unwrap_used, clippy::case_sensitive_file_extension_comparisons)]
+#[test]
+fn bench_tokens() {
+    for file in fs::read_dir("./data")
+        .unwrap()
+        .map(|file| file.unwrap().file_name().into_string().unwrap())
+        .filter(|x| x.starts_with("bench-") && x.ends_with(".c"))
+    {
+        let content = fs::read_to_string(format!("./data/{file}")).unwrap();
+        let Res { result, errors } =
+            lexer::lex_file(&content, FileId::default(), &LexingOptions::default());
+        let mut lexed = String::new();
+        for token in &result {
+            let location = token.get_location();
+            writeln!(
+                lexed,
+                "{}..{} {}",
+                location.start(),
+                location.end(),
+                token.get_value()
+            )
+            .unwrap();
+        }
+        for error in errors {
+            let (location, message, level) = error.get();
+            writeln!(
+                lexed,
+                "{}..{} {level}: {message}",
+                location.start(),
+                location.end()
+            )
+            .unwrap();
+        }
+        let expected = file.replace(".c", ".tokens");
+        assert!(
+            lexed == fs::read_to_string(format!("./data/{expected}")).unwrap(),
+            "{file} isn't lexed as recorded in {expected}"
+        );
+    }
+}
+
+/// Returns the displayed tokens and errors of the preprocessed `source`.
+fn preprocess_source(source: &str, options: &LexingOptions) -> (Vec<String>, Vec<String>) {
+    preprocess_file("test.c", source, options, &mut PreprocessorState::new())
+}
+
+/// Same as [`preprocess_source`], for the file at `path`.
+fn preprocess_file(
+    path: &str,
+    source: &str,
+    options: &LexingOptions,
+    state: &mut PreprocessorState,
+) -> (Vec<String>, Vec<String>) {
+    let mut config = PreprocessorConfig::default();
+    config.set_lexing_options(options.clone());
+    preprocess_with(path, source, &config, state)
+}
+
+/// Same as [`preprocess_file`], with `config`.
+fn preprocess_with(
+    path: &str,
+    source: &str,
+    config: &PreprocessorConfig,
+    state: &mut PreprocessorState,
+) -> (Vec<String>, Vec<String>) {
+    let mut source_map = SourceMap::default();
+    let file_id = source_map.add_file(path.to_owned(), source.to_owned());
+    let content = source_map.source(file_id);
+    let Res {
+        result: lexed,
+        mut errors,
+    } = lexer::lex_file(&content, file_id, config.lexing_options());
+    let Res {
+        result: tokens,
+        errors: preprocessor_errors,
+    } = preprocess(lexed, &mut source_map, config, state);
+    errors.extend(preprocessor_errors);
+    (
+        tokens
+            .iter()
+            .map(|token| token.get_value().to_string())
+            .collect(),
+        errors.into_iter().map(|error| error.get().1).collect(),
+    )
+}
+
+#[test]
+fn timestamp_format() {
+    assert_eq!(
+        format_timestamp(0),
+        ("Jan  1 1970".to_owned(), "00:00:00".to_owned())
+    );
+    assert_eq!(
+        format_timestamp(1_700_000_000),
+        ("Nov 14 2023".to_owned(), "22:13:20".to_owned())
+    );
+    assert_eq!(format_timestamp(951_782_400).0, "Feb 29 2000");
+    assert_eq!(format_timestamp(1_709_596_800).0, "Mar  5 2024");
+}
+
+#[test]
+fn stdc_version() {
+    for (standard, expected) in [
+        (Standard::C89, "__STDC_VERSION__"),
+        (Standard::C99, "199901L"),
+        (Standard::C11, "201112L"),
+        (Standard::C17, "201710L"),
+        (Standard::C23, "202311L"),
+    ] {
+        let options = LexingOptions {
+            standard,
+            ..Default::default()
+        };
+        let (tokens, errors) = preprocess_source("__STDC_VERSION__", &options);
+        assert_eq!(tokens, [expected], "{}", standard.repr());
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+}
+
+#[test]
+fn has_c_attribute() {
+    for (attribute, expected) in [
+        ("nodiscard", "202003L"),
+        ("__nodiscard__", "202003L"),
+        ("deprecated", "201904L"),
+        ("noreturn", "202202L"),
+        ("gnu::unused", "0L"),
+        ("unknown", "0L"),
+    ] {
+        let (tokens, errors) = preprocess_source(
+            &format!("__has_c_attribute({attribute})"),
+            &LexingOptions::default(),
+        );
+        assert_eq!(tokens, [expected], "{attribute}");
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+}
+
+#[test]
+fn builtin_macros() {
+    let (tokens, errors) = preprocess_source(
+        "__FILE__ __LINE__\n\n__LINE__ \\\n__LINE__\n#line 20 \"x.c\"\n__LINE__ __FILE__\n__COUNTER__ __COUNTER__ __COUNTER__",
+        &LexingOptions::default(),
+    );
+    assert_eq!(
+        tokens,
+        ["\"test.c\"", "1", "3", "4", "20", "\"x.c\"", "0", "1", "2"]
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    // the counter goes on in the files sharing the state, like included files
+    let mut state = PreprocessorState::new();
+    preprocess_file("a.c", "__COUNTER__", &LexingOptions::default(), &mut state);
+    let (counted, _) = preprocess_file("b.c", "__COUNTER__", &LexingOptions::default(), &mut state);
+    assert_eq!(counted, ["1"]);
+}
+
+#[allow(clippy::unwrap_used)]
+#[test]
+fn has_include() {
+    let dir = test_dir("has-include");
+    fs::create_dir_all(dir.join("inc")).unwrap();
+    fs::write(dir.join("local.h"), "").unwrap();
+    fs::write(dir.join("inc/angled.h"), "").unwrap();
+    let mut config = PreprocessorConfig::default();
+    config.add_include_dir(dir.join("inc"));
+    for (source, expected) in [
+        ("__has_include(\"local.h\")", "1"),
+        ("__has_include(\"missing.h\")", "0"),
+        ("__has_include(<angled.h>)", "1"),
+        ("__has_include(\"angled.h\")", "1"),
+        // angled names aren't looked up next to the current file
+        ("__has_include(<local.h>)", "0"),
+        ("__has_include(\"inc\")", "0"),
+    ] {
+        let (tokens, errors) = preprocess_with(
+            &dir.join("main.c").to_string_lossy(),
+            source,
+            &config,
+            &mut PreprocessorState::new(),
+        );
+        assert_eq!(tokens, [expected], "{source}");
+        assert!(errors.is_empty(), "{source}: {errors:?}");
+    }
+    let (_, errors) = preprocess_source("__has_include(local.h)", &LexingOptions::default());
+    assert_eq!(
+        errors,
+        ["Expected a header name, of the form \"file\" or <file>."]
+    );
+}
+
+#[test]
+fn target_macros() {
+    let source =
+        "__CHAR_BIT__ __SIZEOF_INT__ __SIZEOF_LONG_LONG__ __SIZEOF_DOUBLE__ __SIZEOF_POINTER__";
+    let mut config = PreprocessorConfig::default();
+    // they aren't defined by default
+    let (undefined, _) = preprocess_with("test.c", source, &config, &mut PreprocessorState::new());
+    assert_eq!(undefined, source.split(' ').collect::<Vec<_>>());
+    config.define_target_macros();
+    let (tokens, errors) =
+        preprocess_with("test.c", source, &config, &mut PreprocessorState::new());
+    let pointer = mem::size_of::<usize>().to_string();
+    assert_eq!(tokens, ["8", "4", "8", "8", pointer.as_str()]);
+    assert!(errors.is_empty(), "{errors:?}");
+    let arch = if cfg!(target_arch = "x86_64") {
+        "__x86_64__"
+    } else if cfg!(target_arch = "aarch64") {
+        "__aarch64__"
+    } else {
+        return;
+    };
+    let (defined, _) = preprocess_with(
+        "test.c",
+        &format!("{arch} __LP64__ __i386__"),
+        &config,
+        &mut PreprocessorState::new(),
+    );
+    assert_eq!(defined, ["1", "1", "__i386__"]);
+}
+
+#[test]
+fn dependency_rule() {
+    let dependencies = [
+        PathBuf::from("src/main.c"),
+        PathBuf::from("inc/my header.h"),
+        PathBuf::from("inc/#x$.h"),
+    ];
+    assert_eq!(
+        make_rule("main.o", &dependencies, false),
+        "main.o: src/main.c inc/my\\ header.h inc/\\#x$$.h\n"
+    );
+    assert_eq!(
+        make_rule("main.o", &dependencies, true),
+        "main.o: src/main.c inc/my\\ header.h inc/\\#x$$.h\n\ninc/my\\ header.h:\n\ninc/\\#x$$.h:\n"
+    );
+    let long = PathBuf::from("a".repeat(30));
+    assert_eq!(
+        make_rule("t.o", &[long.clone(), long.clone(), long], false),
+        format!("t.o: {0} {0} \\\n {0}\n", "a".repeat(30))
+    );
+}
+
+/// Creates an empty temporary directory for the test `name`.
+#[allow(clippy::unwrap_used)]
+fn test_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("expressions-{name}"));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[allow(clippy::unwrap_used)]
+#[test]
+fn include() {
+    let dir = test_dir("include");
+    fs::write(dir.join("a.h"), "#include \"b.h\"\nint a;\n").unwrap();
+    fs::write(dir.join("b.h"), "int b;\n").unwrap();
+    let main = dir.join("main.c");
+    let mut state = PreprocessorState::new();
+    state.add_dependency(main.clone());
+    let (tokens, errors) = preprocess_file(
+        &main.to_string_lossy(),
+        "#include \"a.h\"\n#include <missing.h>\nint main;\n",
+        &LexingOptions::default(),
+        &mut state,
+    );
+    assert_eq!(
+        tokens,
+        ["int", "b", ";", "int", "a", ";", "int", "main", ";"]
+    );
+    assert_eq!(errors, ["Included file 'missing.h' not found."]);
+    assert_eq!(
+        state.dependencies(),
+        [main, dir.join("a.h"), dir.join("b.h")]
+    );
+}
+
+#[test]
+fn header_names() {
+    for (source, expected) in [
+        (
+            "#include <gnu/stubs-64.h>",
+            ["#", "include", "<gnu/stubs-64.h>"].as_slice(),
+        ),
+        (
+            "%:embed <my file.bin> limit(1)",
+            &["#", "embed", "<my file.bin>", "limit", "(", "1", ")"],
+        ),
+        (
+            "__has_include(<a.h>)",
+            &["__has_include", "(", "<a.h>", ")"],
+        ),
+        // only in `#include`, `#embed` and `__has_include`
+        ("a < b > c", &["a", "<", "b", ">", "c"]),
+        (
+            "x #include <a.h>",
+            &["x", "#", "include", "<", "a", ".", "h", ">"],
+        ),
+        ("#include <a.h", &["#", "include", "<", "a", ".", "h"]),
+    ] {
+        let (tokens, errors) = lex_source(source, &LexingOptions::default());
+        assert_eq!(tokens, expected, "{source:?}");
+        assert!(errors.is_empty(), "{source:?}: {errors:?}");
+    }
+    let (tokens, errors) = preprocess_source(
+        "#include <gnu/stubs-64.h>\n__has_include(<my file.h>) __has_include( <stdio.h> )",
+        &LexingOptions::default(),
+    );
+    assert_eq!(tokens, ["0", "0"]);
+    assert_eq!(errors, ["Included file 'gnu/stubs-64.h' not found."]);
+}
+
+#[allow(clippy::unwrap_used)]
+#[test]
+fn recursive_include() {
+    let dir = test_dir("recursive-include");
+    fs::write(dir.join("self.h"), "#include \"self.h\"\n").unwrap();
+    let (tokens, errors) = preprocess_file(
+        &dir.join("main.c").to_string_lossy(),
+        "#include \"self.h\"\n",
+        &LexingOptions::default(),
+        &mut PreprocessorState::new(),
+    );
+    assert!(tokens.is_empty());
+    assert_eq!(
+        errors,
+        ["'#include' nested more than 200 times: the inclusions are probably recursive."]
+    );
+}
+
+#[allow(clippy::unwrap_used)]
+#[test]
+fn pragma_once() {
+    let dir = test_dir("pragma-once");
+    fs::write(dir.join("once.h"), "#pragma once\nint once;\n").unwrap();
+    fs::write(dir.join("twice.h"), "int twice;\n").unwrap();
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(
+        dir.join("a.h"),
+        "#include \"once.h\"\n#include \"twice.h\"\n",
+    )
+    .unwrap();
+    let (tokens, errors) = preprocess_file(
+        &dir.join("main.c").to_string_lossy(),
+        "#include \"a.h\"\n#include \"./sub/../once.h\"\n#include \"twice.h\"\n",
+        &LexingOptions::default(),
+        &mut PreprocessorState::new(),
+    );
+    assert_eq!(
+        tokens,
+        ["int", "once", ";", "int", "twice", ";", "int", "twice", ";"]
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+}
+
+#[allow(clippy::unwrap_used)]
+#[test]
+fn embed_limit() {
+    let dir = test_dir("embed-limit");
+    fs::write(dir.join("data.bin"), [1, 2, 3, 4, 5, 6]).unwrap();
+    let (tokens, errors) = preprocess_file(
+        &dir.join("main.c").to_string_lossy(),
+        "#embed \"data.bin\" limit(2)\n#embed \"data.bin\" limit(0) if_empty(7)\n",
+        &LexingOptions::default(),
+        &mut PreprocessorState::new(),
+    );
+    assert_eq!(tokens, ["1", ",", "2", "7"]);
+    assert!(errors.is_empty(), "{errors:?}");
+}
+
+#[allow(clippy::unwrap_used)]
+#[test]
+fn embed_parameters() {
+    let dir = test_dir("embed-parameters");
+    fs::write(dir.join("data.bin"), [1, 2, 3]).unwrap();
+    fs::write(dir.join("empty.bin"), []).unwrap();
+    for (source, expected) in [
+        (
+            "#embed \"data.bin\" prefix(x, (char)) suffix(, 0) if_empty(-1)",
+            [
+                "x", ",", "(", "char", ")", "1", ",", "2", ",", "3", ",", "0",
+            ]
+            .as_slice(),
+        ),
+        (
+            "#embed \"data.bin\" __prefix__(+) limit(1) __suffix__(;)",
+            &["+", "1", ";"],
+        ),
+        // the prefix and suffix are only added around a non-empty resource
+        (
+            "#embed \"data.bin\" prefix(x) limit(0) suffix(y) if_empty(-1)",
+            &["-", "1"],
+        ),
+        ("#embed \"data.bin\" prefix(x) limit(0) suffix(y)", &[]),
+        ("#embed \"empty.bin\" prefix(x) if_empty(z)", &["z"]),
+    ] {
+        let (tokens, errors) = preprocess_file(
+            &dir.join("main.c").to_string_lossy(),
+            source,
+            &LexingOptions::default(),
+            &mut PreprocessorState::new(),
+        );
+        assert_eq!(tokens, expected, "{source}");
+        assert!(errors.is_empty(), "{source}: {errors:?}");
+    }
+}
+
+/// Lexes and preprocesses `source`, and returns the output of `-E`.
+fn preprocessed_text(source: &str, options: &LexingOptions) -> String {
+    let mut source_map = SourceMap::default();
+    let file_id = source_map.add_file("test.c".to_owned(), source.to_owned());
+    let content = source_map.source(file_id);
+    let Res { result: lexed, .. } = lexer::lex_file(&content, file_id, options);
+    let Res { result: tokens, .. } = preprocess(
+        lexed,
+        &mut source_map,
+        &PreprocessorConfig::default(),
+        &mut PreprocessorState::new(),
+    );
+    preprocessed_output(&tokens, &source_map)
+}
+
+#[test]
+fn preprocessed_comments() {
+    let options = LexingOptions {
+        keep_comments: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        preprocessed_text(
+            "int a; /* one\ntwo */ int b;\n// line\nint c; /**/\n",
+            &options
+        ),
+        "# 1 \"test.c\"\nint a;/* one\ntwo */int b;\n// line\nint c;/**/\n"
+    );
+    assert_eq!(
+        preprocessed_text("int a; /* one\ntwo */ int b;\n", &LexingOptions::default()),
+        "# 1 \"test.c\"\nint a;\nint b;\n"
+    );
+}
+
+#[test]
+fn preprocessed_spacing() {
+    assert_eq!(
+        preprocessed_text(
+            "a + +b - -c;\nx . 5 ;y- >z; a & &b; c < <= d;\nint x = 1 , y;\n",
+            &LexingOptions::default()
+        ),
+        "# 1 \"test.c\"\na+ +b- -c;\nx. 5;y- >z;a& &b;c< <=d;\nint x=1,y;\n"
+    );
+}
+
+#[test]
+fn preprocessed_lines() {
+    let source = format!("int a;\n\n\nint b;{}int c;\n", "\n".repeat(12));
+    assert_eq!(
+        preprocessed_text(&source, &LexingOptions::default()),
+        "# 1 \"test.c\"\nint a;\n\n\nint b;\n# 16 \"test.c\"\nint c;\n"
+    );
+}
+
+/// Lexes `source`, and returns the displayed tokens with the error messages.
+fn lex_source(source: &str, options: &LexingOptions) -> (Vec<String>, Vec<String>) {
+    let Res { result, errors } = lexer::lex_file(source, FileId::default(), options);
+    (
+        result
+            .iter()
+            .map(|token| token.get_value().to_string())
+            .collect(),
+        errors.into_iter().map(|error| error.get().1).collect(),
+    )
+}
+
+#[test]
+fn colon_colon() {
+    for (standard, expected) in [
+        (Standard::C17, ["a", "?", "b", ":", ":", "c"].as_slice()),
+        (Standard::C23, ["a", "?", "b", "::", "c"].as_slice()),
+    ] {
+        let options = LexingOptions {
+            standard,
+            ..Default::default()
+        };
+        let (tokens, errors) = lex_source("a ? b::c", &options);
+        assert_eq!(tokens, expected, "{}", standard.repr());
+        assert!(errors.is_empty(), "{errors:?}");
+        let (attribute, _) = preprocess_source("__has_c_attribute(gnu::unused)", &options);
+        assert_eq!(attribute, ["0L"], "{}", standard.repr());
+    }
+}
+
+#[test]
+fn string_code_units() {
+    let text =
+        |encoding, value: &'static str| TokenValue::Str(encoding, StrValue::Text(value.into()));
+    let units =
+        |encoding, value: &[u32]| TokenValue::Str(encoding, StrValue::Units(value.to_vec()));
+    for (source, expected) in [
+        ("\"\\xC3\\xA9\"", text(Encoding::Ordinary, "\u{e9}")),
+        ("\"\\xFF\"", units(Encoding::Ordinary, &[0xFF])),
+        ("u8\"\\xC3\\xA9\"", text(Encoding::Utf8, "\u{e9}")),
+        ("u8\"\\303\"", units(Encoding::Utf8, &[0xC3])),
+        ("u\"\\xD800\"", units(Encoding::Utf16, &[0xD800])),
+        ("u\"\\xD83D\\xDE00\"", text(Encoding::Utf16, "\u{1f600}")),
+        (
+            "u\"\u{e9}\\xD800\"",
+            units(Encoding::Utf16, &[0xE9, 0xD800]),
+        ),
+        ("U\"a\\x1F600\"", text(Encoding::Utf32, "a\u{1f600}")),
+        ("U\"\\xFFFFFFFF\"", units(Encoding::Utf32, &[0xFFFF_FFFF])),
+        ("L\"\\x41\\n\"", text(Encoding::Wide, "A\n")),
+    ] {
+        let Res { result, errors } =
+            lexer::lex_file(source, FileId::default(), &LexingOptions::default());
+        assert!(errors.is_empty(), "{source}: {errors:?}");
+        let values: Vec<_> = result.into_iter().map(Token::into_value).collect();
+        assert_eq!(values, [expected], "{source}");
+    }
+    let Res { errors, .. } =
+        lexer::lex_file("u8\"\\x100\"", FileId::default(), &LexingOptions::default());
+    assert_eq!(errors.len(), 1);
+}
+
+/// Returns the lexeme of each token, with whether it borrows `source`.
+fn lexemes(source: &str, options: &LexingOptions) -> Vec<(String, bool)> {
+    let Res { result, .. } = lexer::lex_file(source, FileId::default(), options);
+    result
+        .into_iter()
+        .map(|mut token| {
+            let lexeme = token.lexeme_mut();
+            let borrowed =
+                matches!(lexeme, Cow::Borrowed(text) if source.as_bytes().as_ptr_range().contains(&text.as_ptr()));
+            (lexeme.to_string(), borrowed)
+        })
+        .collect()
+}
+
+#[test]
+fn source_spelling() {
+    let options = LexingOptions {
+        trigraphs: true,
+        ..Default::default()
+    };
+    // the tokens are spelled as in the source, and borrow it
+    let source = "0x1Fu 1.5e+3f u8\"x\\n\" L'\\0' >>= 0.5 %: ident";
+    let spellings: Vec<(String, bool)> = source
+        .split(' ')
+        .map(|spelling| (spelling.to_owned(), true))
+        .collect();
+    assert_eq!(lexemes(source, &options), spellings);
+    // the spelling is owned when a line splice or a trigraph was removed
+    assert_eq!(
+        lexemes("12\\\n34 >\\\n>= \"a\\\nb\" ??< ??=??= x??/\ny", &options),
+        [
+            ("1234", false),
+            (">>=", false),
+            ("\"ab\"", false),
+            ("{", false),
+            ("##", false),
+            ("xy", false),
+        ]
+        .map(|(lexeme, borrowed)| (lexeme.to_owned(), borrowed))
+    );
+}
+
+/// Lexes `source` and concatenates its string literals.
+fn concat_source(source: &str) -> (Vec<(TokenValue<'static>, String, Span)>, Vec<String>) {
+    let mut source_map = SourceMap::default();
+    let file_id = source_map.add_file("test.c".to_owned(), source.to_owned());
+    let content = source_map.source(file_id);
+    let options = LexingOptions {
+        trivia: true,
+        ..Default::default()
+    };
+    let Res { result: lexed, .. } = lexer::lex_file(&content, file_id, &options);
+    let Res { result, errors } = concat_strings(lexed, &source_map);
+    (
+        result
+            .into_iter()
+            .map(|token| {
+                let lexeme = token.get_lexeme().to_owned();
+                let (value, location) = token.into_value_location();
+                (value.into_owned(), lexeme, location)
+            })
+            .collect(),
+        errors.into_iter().map(|error| error.get().1).collect(),
+    )
+}
+
+#[allow(clippy::panic)]
+#[test]
+fn string_concatenation() {
+    let (tokens, errors) = concat_source("x = \"a\" /* c */ \"b\"\n  L\"c\\n\";");
+    assert!(errors.is_empty(), "{errors:?}");
+    let [_, _, (value, lexeme, location), _] = tokens.as_slice() else {
+        panic!("{tokens:?}");
+    };
+    assert_eq!(
+        *value,
+        TokenValue::Str(Encoding::Wide, StrValue::Text("abc\n".into()))
+    );
+    assert_eq!(lexeme, "\"a\" /* c */ \"b\"\n  L\"c\\n\"");
+    assert_eq!((location.start(), location.end()), (4, 28));
+    let (units, _) = concat_source("\"a\" u\"\\xD800\" \"b\"");
+    assert_eq!(
+        units.first().map(|token| &token.0),
+        Some(&TokenValue::Str(
+            Encoding::Utf16,
+            StrValue::Units(vec![0x61, 0xD800, 0x62])
+        ))
+    );
+    let (_, incompatible) = concat_source("u8\"a\" L\"b\"");
+    assert_eq!(
+        incompatible,
+        ["Cannot concatenate string literals with incompatible encoding prefixes 'u8' and 'L'."]
+    );
+}
+
+#[test]
+fn invalid_char_escape() {
+    for (source, error) in [
+        (
+            "'\\q'",
+            "Character 'q' can not be escaped, even inside a string or a char.",
+        ),
+        (
+            "'ab\\q'",
+            "Character 'q' can not be escaped, even inside a string or a char.",
+        ),
+        (
+            "u'\\x10000'",
+            "Escape sequence '\\x10000' is out of range for type 'char16_t'.",
+        ),
+    ] {
+        let (tokens, errors) = lex_source(source, &LexingOptions::default());
+        assert_eq!(tokens, [source], "{source}");
+        assert_eq!(errors, [error], "{source}");
+    }
+}
+
+#[allow(clippy::panic)]
+#[test]
+fn char_constants() {
+    for (source, value, c_type, warnings) in [
+        (
+            "'ab'",
+            0x6162,
+            "int",
+            ["Multi-character character constant: its value is implementation-defined."].as_slice(),
+        ),
+        ("L'x'", 0x78, "wchar_t", &[]),
+        ("u8'a'", 0x61, "unsigned char", &[]),
+        ("u'x'", 0x78, "char16_t", &[]),
+        ("U'x'", 0x78, "char32_t", &[]),
+        // `char` is signed
+        ("'\\377'", -1, "int", &[]),
+    ] {
+        let Res { result, errors } =
+            lexer::lex_file(source, FileId::default(), &LexingOptions::default());
+        let Some(TokenValue::Char(constant)) = result.first().map(Token::get_value) else {
+            panic!("{source} isn't a char: {result:?}")
+        };
+        assert_eq!(constant.value(), value, "{source}");
+        assert_eq!(constant.c_type(), c_type, "{source}");
+        let messages: Vec<_> = errors.into_iter().map(|error| error.get().1).collect();
+        assert_eq!(messages, warnings, "{source}");
+    }
+}
+
+#[test]
+fn unknown_string_escape() {
+    let (tokens, errors) = lex_source("\"a\\qb\"", &LexingOptions::default());
+    assert_eq!(tokens, ["\"aqb\""]);
+    assert_eq!(
+        errors,
+        ["Unknown escape sequence '\\q': 'q' is used instead."]
+    );
+}
+
+#[test]
+fn borrowed_lexemes() {
+    let options = LexingOptions {
+        trigraphs: true,
+        ..Default::default()
+    };
+    let source = "int ab\\\ncd = \"s\\n\" + 'c' ??= \"x\\\ny\";";
+    let Res { result, errors } = lexer::lex_file(source, FileId::default(), &options);
+    let messages: Vec<String> = errors.into_iter().map(|error| error.get().1).collect();
+    assert_eq!(messages, ["Trigraph '??=' replaced by '#'."]);
+    let lexemes: Vec<(String, bool)> = result
+        .into_iter()
+        .map(|mut token| {
+            let lexeme = token.lexeme_mut();
+            (lexeme.to_string(), matches!(lexeme, Cow::Borrowed(_)))
+        })
+        .collect();
+    assert_eq!(
+        lexemes,
+        [
+            ("int", true),
+            ("abcd", false),
+            ("=", true),
+            ("\"s\\n\"", true),
+            ("+", true),
+            ("'c'", true),
+            ("#", false),
+            ("\"xy\"", false),
+            (";", true),
+        ]
+        .map(|(lexeme, borrowed)| (lexeme.to_owned(), borrowed))
+    );
+}
+
+fn source_docs(source: &str) -> DocIndex {
+    let options = LexingOptions {
+        keep_comments: true,
+        ..Default::default()
+    };
+    let mut source_map = SourceMap::default();
+    let file_id = source_map.add_file("docs.c".to_owned(), source.to_owned());
+    let Res { result, errors } = lexer::lex_file(source_map.content(file_id), file_id, &options);
+    assert!(errors.is_empty(), "{errors:?}");
+    extract_docs(&result, &source_map)
+}
+
+#[allow(clippy::unwrap_used)]
+#[test]
+fn line_docs() {
+    let docs = source_docs(
+        "/// Adds two numbers.
+///
+/// Overflows wrap.
+/// @param a first term
+/// @param b second
+///        term
+/// @return the sum
+int add(int a, int b) { return a + b; }
+",
+    );
+    let add = docs.get_function("add").unwrap();
+    assert_eq!(add.get_brief(), "Adds two numbers.");
+    assert_eq!(add.get_details(), "Overflows wrap.");
+    assert_eq!(add.get_param("a"), Some("first term"));
+    assert_eq!(add.get_param("b"), Some("second term"));
+    assert_eq!(add.get_returns(), Some("the sum"));
+    assert_eq!(docs.iter().count(), 1);
+}
+
+#[allow(clippy::unwrap_used)]
+#[test]
+fn block_docs() {
+    let docs = source_docs(
+        "/**
+ * @brief A point of the plane.
+ *
+ * Coordinates are in pixels.
+ *
+ * @since 1.2
+ */
+struct point { int x; int y; };
+/**
+ * Size of a point.
+ */
+typedef unsigned long point_size;
+",
+    );
+    let point = docs.get_struct("point").unwrap();
+    assert_eq!(point.get_brief(), "A point of the plane.");
+    assert_eq!(
+        point.get_details(),
+        "Coordinates are in pixels.\n\n@since 1.2"
+    );
+    let size = docs.get(DeclarationKind::Typedef, "point_size").unwrap();
+    assert_eq!(size.get_brief(), "Size of a point.");
+    assert_eq!(size.get_details(), "");
+}
+
+#[allow(clippy::unwrap_used)]
+#[test]
+fn trailing_docs() {
+    let docs = source_docs(
+        "struct list {
+    int len; //!< Ignored, as members aren't declarations.
+};
+int count; //!< Number of lists.
+double ratio; ///< Ratio of the
+              ///< lengths.
+",
+    );
+    assert!(docs.get_struct("list").is_none());
+    let count = docs.get(DeclarationKind::Variable, "count").unwrap();
+    assert_eq!(count.get_brief(), "Number of lists.");
+    let ratio = docs.get(DeclarationKind::Variable, "ratio").unwrap();
+    assert_eq!(ratio.get_brief(), "Ratio of the lengths.");
+    assert_eq!(docs.iter().count(), 2);
+}
+
+#[allow(clippy::unwrap_used)]
+#[test]
+fn detached_docs() {
+    let docs = source_docs(
+        "/// Comment of the file.
+
+int lonely;
+// Not a documentation comment.
+/** Kept. */
+int kept;
+",
+    );
+    assert!(docs.get(DeclarationKind::Variable, "lonely").is_none());
+    let kept = docs.get(DeclarationKind::Variable, "kept").unwrap();
+    assert_eq!(kept.get_brief(), "Kept.");
+    assert_eq!(docs.iter().count(), 1);
+}
+
+#[test]
+fn displayed_docs() {
+    let docs = source_docs(
+        "/** Counter. */
+int count;
+/**
+ * Adds two numbers.
+ *
+ * Overflows silently.
+ *
+ * @param a first
+ * @param b second
+ * @return the sum
+ */
+int add(int a, int b);
+",
+    );
+    assert_eq!(
+        docs.to_string(),
+        "function add
+    brief: Adds two numbers.
+    param a: first
+    param b: second
+    returns: the sum
+    Overflows silently.
+variable count
+    brief: Counter.
+"
+    );
+}
+
+#[test]
+fn documentation_tags() {
+    let doc = Documentation::parse(
+        "\\brief Short.
+\\param[in] len number of items
+\\returns nothing
+\\note Long
+description.",
+    );
+    assert_eq!(doc.get_brief(), "Short.");
+    assert_eq!(
+        doc.get_params(),
+        [("len".to_owned(), "number of items".to_owned())]
+    );
+    assert_eq!(doc.get_returns(), Some("nothing"));
+    assert_eq!(doc.get_details(), "\\note Long description.");
+}
+
+#[test]
+fn byte_spans() {
+    let source = "ab\n  cd\t e\n\u{e9}t\u{e9} = \"\u{20ac}\u{1f600}\" x\n";
+    let Res { result, errors } =
+        lexer::lex_file(source, FileId::default(), &LexingOptions::default());
+    assert!(errors.is_empty(), "{errors:?}");
+    let lines = LineIndex::new(source);
+    let spans: Vec<_> = result
+        .iter()
+        .map(|token| {
+            let location = token.get_location();
+            (
+                location.text(source),
+                location.start(),
+                location.end(),
+                lines.line_col(source, location.start()),
+            )
+        })
+        .collect();
+    // the offsets are in bytes, and the columns in characters
+    assert_eq!(
+        spans,
+        [
+            ("ab", 0, 2, (1, 1)),
+            ("cd", 5, 7, (2, 3)),
+            ("e", 9, 10, (2, 7)),
+            ("\u{e9}t\u{e9}", 11, 16, (3, 1)),
+            ("=", 17, 18, (3, 5)),
+            ("\"\u{20ac}\u{1f600}\"", 19, 28, (3, 7)),
+            ("x", 29, 30, (3, 12)),
+        ]
+    );
+}
+
+#[test]
+fn source_map_locations() {
+    let mut source_map = SourceMap::default();
+    let first = source_map.add_file("first.c".to_owned(), "int a;\n  int b;\n".to_owned());
+    let second = source_map.add_file(
+        "second.c".to_owned(),
+        "a\n#line 10 \"x.c\"\nb\nc\n#line 20\nd".to_owned(),
+    );
+    // the same offset is resolved in the file of the span
+    assert_eq!(source_map.line_col(Span::point(first, 11)), (2, 5));
+    assert_eq!(source_map.line_col(Span::point(second, 11)), (2, 10));
+    assert_eq!(source_map.name(second), "second.c");
+    assert_eq!(source_map.line(first, 2), "  int b;");
+    assert_eq!(source_map.line(second, 3), "b");
+    // `#line` only remaps the lines that follow it, in its own file
+    source_map.add_line_marker(Span::new(second, 2, 16), 10, Some("x.c".to_owned()));
+    source_map.add_line_marker(Span::new(second, 21, 29), 20, None);
+    let locations: Vec<_> = [
+        (second, 0),
+        (second, 17),
+        (second, 19),
+        (second, 30),
+        (first, 11),
+    ]
+    .into_iter()
+    .map(|(file_id, offset)| source_map.display(Span::point(file_id, offset)))
+    .collect();
+    assert_eq!(
+        locations,
+        [
+            "second.c:1:1",
+            "x.c:10:1",
+            "x.c:11:1",
+            "x.c:20:1",
+            "first.c:2:5"
+        ]
+    );
+    // the physical location is kept
+    assert_eq!(source_map.line_col(Span::point(second, 30)), (6, 1));
+}
+
+#[test]
+fn spliced_spans() {
+    let options = LexingOptions {
+        trigraphs: true,
+        ..Default::default()
+    };
+    for (source, expected) in [
+        // at the start and at the end of a token
+        (
+            "\\\nfoo\\\n bar",
+            [("foo", 2, 5), ("bar", 8, 11)].as_slice(),
+        ),
+        // back-to-back splices
+        ("fo\\\n\\\no", &[("foo", 0, 7)]),
+        (
+            "\"a\\\n\\\nb\" 'c\\\n'",
+            &[("\"ab\"", 0, 8), ("'c'", 9, 14)],
+        ),
+        // splices spelled with the `??/` trigraph
+        ("a??/\nb c", &[("ab", 0, 6), ("c", 7, 8)]),
+        (
+            "x ??/\n??/\n+= 1",
+            &[("x", 0, 1), ("+=", 10, 12), ("1", 13, 14)],
+        ),
+    ] {
+        let Res { result, errors } = lexer::lex_file(source, FileId::default(), &options);
+        let spans: Vec<(&str, usize, usize)> = result
+            .iter()
+            .map(|token| {
+                let location = token.get_location();
+                (token.get_lexeme(), location.start(), location.end())
+            })
+            .collect();
+        assert_eq!(spans, expected, "{source:?}");
+        for error in errors {
+            assert_eq!(
+                error.get().1,
+                "Trigraph '??/' replaced by '\\'.",
+                "{source:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn gnu_extensions() {
+    for (arguments, gnu) in [
+        (["-std=c11", "test"].as_slice(), false),
+        (&["test"], false),
+        (&["-std=gnu11"], true),
+        // the last standard decides, as with GCC
+        (&["-std=gnu11", "-std=c17"], false),
+        (&["-std=c17", "-std=gnu11"], true),
+        (&["-std=gnu11", "-std=unknown"], true),
+    ] {
+        let args = Args::parse(arguments.iter().map(|arg| (*arg).to_owned()));
+        assert_eq!(args.lexing.extensions.gnu, gnu, "{arguments:?}");
+        let Res { result, .. } =
+            lexer::lex_file("__typeof__(x) y;", FileId::default(), &args.lexing);
+        let keyword = matches!(
+            result.first().map(Token::get_value),
+            Some(TokenValue::Keyword(_))
+        );
+        assert_eq!(keyword, gnu, "{arguments:?}");
+    }
+}
+
+#[test]
+fn identifier_characters() {
+    for (source, tokens, error) in [
+        // NFC equivalence
+        ("a\\u0301 \u{e1}", ["\u{e1}", "\u{e1}"].as_slice(), None),
+        ("_\u{3b1}1 x\u{b7}", &["_\u{3b1}1", "x\u{b7}"], None),
+        (
+            "\\u0030",
+            &["\\u0030"],
+            Some("Invalid universal character name '\\u0030' in an identifier: it designates a character of the basic character set."),
+        ),
+        (
+            "\u{661}abc",
+            &["\u{661}", "abc"],
+            Some("Character '\u{661}' not supported in context of a 'start of line'."),
+        ),
+        (
+            "a\\u12",
+            &["a", "\\u12"],
+            Some("Incomplete universal character name '\\u12': it must have 4 hexadecimal digits."),
+        ),
+        (
+            "a\\U0001F6g",
+            &["a", "\\U0001F6", "g"],
+            Some("Incomplete universal character name '\\U0001F6': it must have 8 hexadecimal digits."),
+        ),
+    ] {
+        let (lexed, errors) = lex_source(source, &LexingOptions::default());
+        assert_eq!(lexed, tokens, "{source:?}");
+        assert_eq!(errors, error.as_slice(), "{source:?}");
+    }
+    let Res { result, .. } =
+        lexer::lex_file("a\\u12", FileId::default(), &LexingOptions::default());
+    let locations: Vec<(usize, usize)> = result
+        .iter()
+        .map(|token| (token.get_location().start(), token.get_location().end()))
+        .collect();
+    assert_eq!(locations, [(0, 1), (1, 5)]);
+}
+
+fn decode_bytes(bytes: &[u8], encoding: SourceEncoding) -> (String, Vec<(String, Span)>) {
+    let options = LexingOptions {
+        encoding,
+        ..Default::default()
+    };
+    let mut source_map = SourceMap::default();
+    let Res { result, errors } =
+        decode_file(&mut source_map, "decode.c".to_owned(), bytes, &options);
+    (
+        source_map.content(result).to_owned(),
+        errors
+            .into_iter()
+            .map(|error| {
+                let (location, message, _) = error.get();
+                (message, location)
+            })
+            .collect(),
+    )
+}
+
+#[test]
+fn byte_order_mark() {
+    for encoding in [
+        SourceEncoding::Utf8,
+        SourceEncoding::Latin1,
+        SourceEncoding::Windows1252,
+    ] {
+        // the mark makes the file UTF-8, whatever the encoding
+        let (text, errors) = decode_bytes(b"\xEF\xBB\xBFint \xC3\xA9;", encoding);
+        assert_eq!(text, "int \u{e9};", "{}", encoding.repr());
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+    let (text, _) = decode_bytes(b"int \xEF\xBB\xBF;", SourceEncoding::Utf8);
+    assert_eq!(text, "int \u{feff};");
+}
+
+#[test]
+fn normalised_newlines() {
+    let (text, errors) = decode_bytes(b"a\r\n  bc\rd\n\r\n\te\r", SourceEncoding::Utf8);
+    assert_eq!(text, "a\n  bc\nd\n\n\te\n");
+    assert!(errors.is_empty(), "{errors:?}");
+    let mut source_map = SourceMap::default();
+    let file_id = source_map.add_file("newlines.c".to_owned(), text);
+    let Res { result, .. } = lexer::lex_file(
+        source_map.content(file_id),
+        file_id,
+        &LexingOptions::default(),
+    );
+    let positions: Vec<(usize, usize)> = result
+        .iter()
+        .map(|token| source_map.line_col(*token.get_location()))
+        .collect();
+    assert_eq!(positions, [(1, 1), (2, 3), (3, 1), (5, 2)]);
+}
+
+#[test]
+fn windows_1252() {
+    let bytes = b"\x80\x81\x8D\x8F\x90\x9D\x9F\xE9";
+    let (text, errors) = decode_bytes(bytes, SourceEncoding::Windows1252);
+    assert_eq!(
+        text,
+        "\u{20ac}\u{fffd}\u{fffd}\u{fffd}\u{fffd}\u{fffd}\u{178}\u{e9}"
+    );
+    let messages: Vec<String> = errors.into_iter().map(|(message, _)| message).collect();
+    assert_eq!(
+        messages,
+        ["81", "8D", "8F", "90", "9D"].map(|byte| format!(
+            "Invalid Windows-1252 byte sequence '\\x{byte}': replaced by U+FFFD."
+        ))
+    );
+    // the same bytes are the C1 control codes in Latin-1
+    let (latin1, latin1_errors) = decode_bytes(bytes, SourceEncoding::Latin1);
+    assert_eq!(latin1, "\u{80}\u{81}\u{8d}\u{8f}\u{90}\u{9d}\u{9f}\u{e9}");
+    assert!(latin1_errors.is_empty(), "{latin1_errors:?}");
+}
+
+#[test]
+fn invalid_utf8() {
+    let (text, errors) = decode_bytes(b"a\xFFb\xE2\x82\nc\xC3\xA9", SourceEncoding::Utf8);
+    assert_eq!(text, "a\u{fffd}b\u{fffd}\nc\u{e9}");
+    let located: Vec<(String, usize, usize)> = errors
+        .into_iter()
+        .map(|(message, location)| (message, location.start(), location.end()))
+        .collect();
+    assert_eq!(
+        located,
+        [
+            (
+                "Invalid UTF-8 byte sequence '\\xFF': replaced by U+FFFD.".to_owned(),
+                1,
+                4
+            ),
+            (
+                "Invalid UTF-8 byte sequence '\\xE2\\x82': replaced by U+FFFD.".to_owned(),
+                5,
+                8
+            ),
+        ]
+    );
+}
+
+#[test]
+fn line_directive() {
+    for (source, tokens, errors) in [
+        (
+            "#line 5\n__LINE__ __FILE__\n\n__LINE__",
+            ["5", "\"test.c\"", "7"].as_slice(),
+            [].as_slice(),
+        ),
+        (
+            "#line 10 \\\n \"a.c\"\n__LINE__ __FILE__",
+            &["10", "\"a.c\""],
+            &[],
+        ),
+        ("# 3 \"c.c\" 1 2\n__FILE__ __LINE__", &["\"c.c\"", "3"], &[]),
+        (
+            "#line 0\n#line 7 \"b.c\" x\n__LINE__",
+            &["3"],
+            &[
+                "Expected a positive line number after '#line'.",
+                "Unexpected tokens after the file name in '#line' directive.",
+            ],
+        ),
+    ] {
+        let (preprocessed, messages) = preprocess_source(source, &LexingOptions::default());
+        assert_eq!(preprocessed, tokens, "{source:?}");
+        assert_eq!(messages, errors, "{source:?}");
+    }
+}
+
+#[test]
+fn pragmas() {
+    for (source, tokens, errors) in [
+        (
+            "_Pragma(\"omp parallel\") y",
+            ["_Pragma(\"omp parallel\")", "y"].as_slice(),
+            [].as_slice(),
+        ),
+        (
+            "#pragma foo \\\n bar\nx",
+            &["_Pragma(\"foo bar\")", "x"],
+            &[],
+        ),
+        (
+            "_Pragma(once)",
+            &[")"],
+            &["Expected a string literal in '_Pragma' operator."],
+        ),
+        (
+            "#pragma STDC FP_CONTRACT ON\n_Pragma(\"STDC FENV_ROUND FE_UPWARD\")",
+            &[
+                "_Pragma(\"STDC FP_CONTRACT ON\")",
+                "_Pragma(\"STDC FENV_ROUND FE_UPWARD\")",
+            ],
+            &[],
+        ),
+        (
+            "#pragma STDC FP_CONTRACT MAYBE\n#pragma STDC NOPE\n#pragma STDC",
+            &[],
+            &[
+                "Invalid value for pragma 'STDC FP_CONTRACT'. Pragma ignored.",
+                "Unknown pragma 'STDC NOPE'. Pragma ignored.",
+                "Missing pragma name after 'STDC'. Pragma ignored.",
+            ],
+        ),
+    ] {
+        let (preprocessed, messages) = preprocess_source(source, &LexingOptions::default());
+        assert_eq!(preprocessed, tokens, "{source:?}");
+        assert_eq!(messages, errors, "{source:?}");
+    }
+}
+
+#[test]
+fn directive_lines() {
+    for (source, tokens) in [
+        // a `#` after a line splice or a comment isn't at the start of a line
+        ("x \\\n# define y", ["x", "#", "define", "y"].as_slice()),
+        ("a /*\n*/ # pragma once", &["a", "#", "pragma", "once"]),
+        // the new-line before the comment is
+        ("a\n/* b\n*/ # pragma x\ny", &["a", "_Pragma(\"x\")", "y"]),
+        ("#pragma x \\\n\\\n y\nz", &["_Pragma(\"x y\")", "z"]),
+    ] {
+        let (preprocessed, messages) = preprocess_source(source, &LexingOptions::default());
+        assert_eq!(preprocessed, tokens, "{source:?}");
+        assert!(messages.is_empty(), "{source:?}: {messages:?}");
+    }
+}
+
+#[test]
+fn exponent_signs() {
+    for (source, expected) in [
+        (
+            "x = 0.5e-3 + 0e+1",
+            ["x", "=", "0.0005", "+", "0.0"].as_slice(),
+        ),
+        ("1.e+5 1E-2f", &["100000.0", "0.01f"]),
+        ("0x1p-3 0x1.8p+1", &["0.125", "3.0"]),
+        // `e` is a digit in hexadecimal numbers
+        ("0x1e+1", &["30", "+", "1"]),
+    ] {
+        let (tokens, errors) = lex_source(source, &LexingOptions::default());
+        assert_eq!(tokens, expected, "{source:?}");
+        assert!(errors.is_empty(), "{source:?}: {errors:?}");
+    }
+}
+
+#[test]
+fn end_of_file_errors() {
+    for (source, message, span) in [
+        (
+            "a /* b\n c",
+            "Unterminated comment: missing closing '*/' before the end of the file.",
+            2..4,
+        ),
+        (
+            "a \"b",
+            "Unterminated string literal: missing closing quote before the end of the line.",
+            2..4,
+        ),
+        (
+            "a 'b",
+            "Unterminated character constant: missing closing quote before the end of the line.",
+            2..4,
+        ),
+        (
+            "a \\",
+            "Stray '\\' at the end of the file: a line splice must be followed by a line.",
+            2..3,
+        ),
+        (
+            "a \\\n",
+            "Stray '\\' at the end of the file: a line splice must be followed by a line.",
+            2..3,
+        ),
+    ] {
+        let Res { errors, .. } =
+            lexer::lex_file(source, FileId::default(), &LexingOptions::default());
+        let found: Vec<_> = errors
+            .into_iter()
+            .map(|error| {
+                let (location, text, _) = error.get();
+                (text, location.start()..location.end())
+            })
+            .collect();
+        assert_eq!(found, [(message.to_owned(), span)], "{source:?}");
+    }
+}
+
+#[test]
+fn error_recovery() {
+    for (source, tokens, nb_errors) in [
+        ("a @ b $ c", ["a", "@", "b", "$", "c"].as_slice(), 2),
+        ("a@b$c`d", &["a", "@", "b", "$", "c", "`", "d"], 3),
+    ] {
+        let Res { result, errors } =
+            lexer::lex_file(source, FileId::default(), &LexingOptions::default());
+        let values: Vec<_> = result
+            .iter()
+            .map(|token| token.get_value().to_string())
+            .collect();
+        assert_eq!(values, tokens, "{source:?}");
+        // an error token is emitted at each error, and the identifiers are kept
+        let error_spans: Vec<_> = result
+            .iter()
+            .filter(|token| matches!(token.get_value(), TokenValue::Error(_)))
+            .map(|token| *token.get_location())
+            .collect();
+        let spans: Vec<_> = errors.into_iter().map(|error| error.get().0).collect();
+        assert_eq!(spans.len(), nb_errors, "{source:?}");
+        assert_eq!(error_spans, spans, "{source:?}");
+    }
+}
+
+/// Returns whether each token of `source` is a keyword, with the errors.
+fn keyword_tokens(source: &str, options: &LexingOptions) -> (Vec<bool>, Vec<String>) {
+    let Res { result, errors } = lexer::lex_file(source, FileId::default(), options);
+    (
+        result
+            .iter()
+            .map(|token| matches!(token.get_value(), TokenValue::Keyword(_)))
+            .collect(),
+        errors.into_iter().map(|error| error.get().1).collect(),
+    )
+}
+
+#[test]
+fn standard_features() {
+    for (standard, source, keywords, errors) in [
+        (Standard::C89, "restrict inline _Bool", [false; 3].as_slice(), [].as_slice()),
+        (Standard::C99, "restrict inline _Bool", &[true; 3], &[]),
+        (
+            Standard::C89,
+            "a // b",
+            &[false],
+            &["Line comments ('//') are not allowed before C99. Use a block comment ('/* */') instead."],
+        ),
+        (Standard::C99, "a // b", &[false], &[]),
+        (
+            Standard::C17,
+            "0b101",
+            &[false],
+            &["Invalid number constant type: binary constants are not allowed before C23."],
+        ),
+        (Standard::C23, "0b101", &[false], &[]),
+        (Standard::C17, "_Bool _Alignas", &[true; 2], &[]),
+        (
+            Standard::C23,
+            "_Bool _Alignas",
+            &[true; 2],
+            &[
+                "'_Bool' is deprecated in C23: use 'bool' instead.",
+                "'_Alignas' is deprecated in C23: use 'alignas' instead.",
+            ],
+        ),
+    ] {
+        let options = LexingOptions {
+            standard,
+            ..Default::default()
+        };
+        let (found, messages) = keyword_tokens(source, &options);
+        assert_eq!(found, keywords, "{source:?} in {}", standard.repr());
+        assert_eq!(messages, errors, "{source:?} in {}", standard.repr());
+    }
+}
+
+#[test]
+fn dialect_keywords() {
+    // GNU, GNU and MSVC, MSVC
+    let source = "__attribute__ __inline __int64";
+    for (arguments, standard, keywords) in [
+        (
+            ["-std=c99"].as_slice(),
+            Standard::C99,
+            [false, false, false],
+        ),
+        (&["-std=gnu89"], Standard::C89, [true, true, false]),
+        (&["-std=gnu11"], Standard::C11, [true, true, false]),
+        (&["-std=gnu23"], Standard::C23, [true, true, false]),
+        (&["-fms-extensions"], Standard::C23, [false, true, true]),
+        (
+            &["-std=gnu17", "-fms-extensions"],
+            Standard::C17,
+            [true, true, true],
+        ),
+    ] {
+        let args = Args::parse(arguments.iter().map(|arg| (*arg).to_owned()));
+        assert_eq!(args.lexing.standard, standard, "{arguments:?}");
+        let (found, messages) = keyword_tokens(source, &args.lexing);
+        assert_eq!(found, keywords, "{arguments:?}");
+        assert!(messages.is_empty(), "{arguments:?}: {messages:?}");
+    }
+}
+
+#[test]
+fn digraphs() {
+    let (tokens, errors) = lex_source("<: :> <% %> %: %:%: a::b", &LexingOptions::default());
+    assert_eq!(tokens, ["[", "]", "{", "}", "#", "##", "a", "::", "b"]);
+    assert!(errors.is_empty(), "{errors:?}");
+    // a digraph is spelled as in the source
+    let Res { result, .. } =
+        lexer::lex_file("%:%: <%", FileId::default(), &LexingOptions::default());
+    let lexemes: Vec<_> = result.iter().map(Token::get_lexeme).collect();
+    assert_eq!(lexemes, ["%:%:", "<%"]);
+}
+
+#[test]
+fn ignored_trigraphs() {
+    let (tokens, errors) = lex_source("a ??= ???- \"??/\"", &LexingOptions::default());
+    assert_eq!(tokens, ["a", "?", "?", "=", "?", "?", "?", "-", "\"??/\""]);
+    assert_eq!(
+        errors,
+        [
+            "Trigraph '??=' ignored: use '-trigraphs' to replace it.",
+            "Trigraph '??-' ignored: use '-trigraphs' to replace it.",
+            "Trigraph '??/' ignored: use '-trigraphs' to replace it.",
+        ]
+    );
+}
+
+fn pulled_item(item: &Result<Token, CompileError>) -> String {
+    match item {
+        Ok(token) => format!("{} {:?}", token.get_value(), token.get_location()),
+        Err(error) => format!("{error:?}"),
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+#[test]
+fn pull_lexer() {
+    let options = LexingOptions::default();
+    for source in [generated_source(5), "a\nb @ c\n/* d".to_owned()] {
+        let Res { result, errors } = lexer::lex_file(&source, FileId::default(), &options);
+        let (found, tokens): (Vec<_>, Vec<_>) =
+            lexer::Lexer::new(&source, FileId::default(), &options)
+                .map(|item| pulled_item(&item))
+                .partition(|item| item.starts_with("CompileError"));
+        let expected: Vec<_> = result
+            .into_iter()
+            .map(|token| pulled_item(&Ok(token)))
+            .collect();
+        assert_eq!(tokens, expected);
+        let expected_errors: Vec<_> = errors
+            .into_iter()
+            .map(|error| pulled_item(&Err(error)))
+            .collect();
+        assert_eq!(found, expected_errors);
+    }
+    // the errors of a line come before its tokens
+    let is_ok: Vec<_> = lexer::Lexer::new("a\nb @ c", FileId::default(), &options)
+        .map(|item| item.is_ok())
+        .collect();
+    assert_eq!(is_ok, [true, false, true, true, true]);
+    // peeking doesn't consume the item
+    let mut lexer = lexer::Lexer::new("a b", FileId::default(), &options);
+    let peeked = pulled_item(lexer.peek().unwrap());
+    assert_eq!(pulled_item(lexer.peek().unwrap()), peeked);
+    assert_eq!(pulled_item(&lexer.next().unwrap()), peeked);
+    assert!(peeked.starts_with("a "), "{peeked}");
+    assert_eq!(lexer.next().unwrap().unwrap().get_value().to_string(), "b");
+    assert!(lexer.peek().is_none());
+    assert!(lexer.next().is_none());
+    // stopping early, then resuming where it stopped
+    let source = generated_source(10_000);
+    let mut stopped = lexer::Lexer::new(&source, FileId::default(), &options);
+    let first: Vec<_> = stopped
+        .by_ref()
+        .take(3)
+        .map(|item| item.unwrap().get_value().to_string())
+        .collect();
+    assert_eq!(first, ["#", "include", "<stdio.h>"]);
+    assert_eq!(
+        stopped.next().unwrap().unwrap().get_value().to_string(),
+        "static"
+    );
+}